@@ -0,0 +1,160 @@
+use anyhow::Result;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::path::{Path, PathBuf};
+
+use crate::ast::{ImportEdge, UnresolvedImport};
+use crate::config::Config;
+use crate::source::SourceProvider;
+use crate::utils::LookupPaths;
+use crate::workspace::WorkspaceMember;
+use crate::{fs, graph, interpreter, snob_debug, utils, workspace};
+
+/// The directories snob crawls and resolves imports from
+#[derive(Debug)]
+pub struct Layout {
+    pub workspace_members: Vec<WorkspaceMember>,
+    pub lookup_paths: LookupPaths,
+    pub crawl_roots: Vec<PathBuf>,
+}
+
+impl Layout {
+    /// # Arguments
+    /// * `current_dir` - The directory snob was run from
+    /// * `target_directory` - The directory snob was asked to analyze
+    /// * `python` - The interpreter to query for `sys.path`, if any
+    pub fn new(
+        git_root: &Path,
+        current_dir: &Path,
+        target_directory: &Path,
+        python: Option<&Path>,
+    ) -> Result<Self> {
+        let workspace_members = workspace::discover_workspace_members(git_root)?;
+        let mut lookup_paths = if let Some(python) = python {
+            interpreter::get_interpreter_lookup_paths(python, current_dir, git_root)?
+        } else {
+            utils::get_python_local_lookup_paths(current_dir, git_root)
+        };
+        workspace::extend_lookup_paths(&mut lookup_paths, &workspace_members);
+        snob_debug!("Python lookup paths: {:?}", lookup_paths);
+
+        // crawl the target directory along with every lookup root
+        let workspace_roots = workspace_members
+            .iter()
+            .map(|m| m.root.clone())
+            .collect::<Vec<_>>();
+        let crawl_roots = fs::get_crawl_roots(target_directory, &lookup_paths, &workspace_roots);
+        snob_debug!("Crawl roots: {:?}", crawl_roots);
+
+        Ok(Self {
+            workspace_members,
+            lookup_paths,
+            crawl_roots,
+        })
+    }
+}
+
+/// The dependency graph of the python files provided by a source
+#[derive(Debug)]
+pub struct Analysis {
+    pub workspace_files: Vec<PathBuf>,
+    /// the crawled files (contains all workspace files)
+    pub project_files: FxHashSet<String>,
+    /// imported file -> importing files
+    pub dependency_graph: FxHashMap<String, FxHashSet<String>>,
+    /// imported file -> the edges to the files importing it, with their import statements
+    pub import_edges: FxHashMap<String, Vec<ImportEdge>>,
+    /// files recognized as tests by their AST (see `ast::TestDetection`)
+    pub test_targets: FxHashSet<String>,
+    /// file -> its imports that could not be resolved to project files
+    pub unresolved_imports: FxHashMap<String, Vec<UnresolvedImport>>,
+    /// files that could not be parsed
+    pub parse_failures: FxHashSet<String>,
+}
+
+impl Analysis {
+    /// # Arguments
+    /// * `deleted_files` - Files imports are still resolved to, which keeps track of the
+    ///   consumers they had before being deleted
+    /// * `base_source` - When provided, its graph is merged into this one (see
+    ///   `graph::build_dependency_graph_from_source`)
+    pub fn new(
+        source: &dyn SourceProvider,
+        layout: &Layout,
+        config: &Config,
+        git_root: &Path,
+        deleted_files: &FxHashSet<String>,
+        base_source: Option<&dyn SourceProvider>,
+    ) -> Result<Self> {
+        let workspace_files = source.list_files(&layout.crawl_roots);
+
+        // these need to retain some sort of order information
+        let mut first_level_components: Vec<PathBuf> =
+            source.first_level_components(&layout.lookup_paths);
+        // deleted modules and packages are still imported by their former consumers
+        for component in fs::get_deleted_first_level_components(deleted_files, &layout.lookup_paths)
+        {
+            if !first_level_components.contains(&component) {
+                first_level_components.push(component);
+            }
+        }
+        snob_debug!("First level components: {:?}", first_level_components);
+
+        snob_debug!(
+            "Crawled {:?} files and {:?} directories",
+            workspace_files.len(),
+            first_level_components.len()
+        );
+
+        let project_files = workspace_files
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect::<FxHashSet<String>>();
+
+        let resolvable_files = project_files
+            .iter()
+            .chain(deleted_files)
+            .cloned()
+            .collect::<FxHashSet<String>>();
+
+        // build dependency graph (remove ignored files)
+        let file_ignores = fs::build_glob_set(&config.files.ignores)?;
+        let parsed = graph::build_dependency_graph(
+            &workspace_files,
+            &resolvable_files,
+            &file_ignores,
+            &first_level_components,
+            git_root,
+            &|file| config.tests.test_detection(file),
+            source,
+        );
+        let mut all_file_imports = parsed.dependencies;
+        if let Some(base_source) = base_source {
+            all_file_imports.extend(graph::build_dependency_graph_from_source(
+                base_source,
+                &layout.crawl_roots,
+                &layout.lookup_paths,
+                &file_ignores,
+                git_root,
+            ));
+        }
+
+        // not deduplicated
+        let import_edges = utils::merge_hashmaps(&mut all_file_imports);
+        let dependency_graph = utils::deduplicate_dependencies(&import_edges);
+        snob_debug!("Dependency graph:");
+        for (k, v) in &dependency_graph {
+            snob_debug!("\t{k} is used by:");
+            v.iter().for_each(|v| snob_debug!("\t\t{v}"));
+        }
+
+        Ok(Self {
+            workspace_files,
+            project_files,
+            dependency_graph,
+            import_edges,
+            test_targets: parsed.test_targets,
+            unresolved_imports: parsed.unresolved_imports,
+            parse_failures: parsed.parse_failures,
+        })
+    }
+}
@@ -1,27 +1,35 @@
 use anyhow::Result;
-use ruff_python_ast::{statement_visitor::StatementVisitor, Mod, StmtImport, StmtImportFrom};
+use ruff_python_ast::{
+    statement_visitor::{walk_stmt, StatementVisitor},
+    Expr, Mod, Stmt, StmtImport, StmtImportFrom,
+};
 use ruff_python_parser::{parse, Mode};
 use std::path::{Path, PathBuf, MAIN_SEPARATOR_STR};
 
 use rustc_hash::{FxHashMap, FxHashSet};
 
 use crate::snob_debug;
+use crate::source::SourceProvider;
 
 #[derive(Debug)]
 pub struct FileImports {
     pub file: PathBuf,
-    pub imports: Vec<Import>,
+    pub imports: Vec<(Import, ImportSite)>,
 }
 
 pub const INIT_FILE: &str = "__init__.py";
 
 impl FileImports {
+    /// Resolve the imports to project files, each with the first statement importing it.
+    ///
+    /// Imports of a first level component (or relative ones) that match none of the project
+    /// files are returned as unresolved, other ones are assumed to be third-party imports.
     pub fn resolve_imports(
         &self,
         project_files: &FxHashSet<String>,
         first_level_components: &[PathBuf],
-    ) -> FxHashSet<String> {
-        let imports = self.imports.iter().filter_map(|import| {
+    ) -> (FxHashMap<String, ImportSite>, Vec<UnresolvedImport>) {
+        let imports = self.imports.iter().filter_map(|(import, site)| {
             if import.is_relative() {
                 // resolve relative imports
                 let path = self
@@ -29,48 +37,69 @@ impl FileImports {
                     .ancestors()
                     .nth(import.level as usize)
                     .expect("Relative import level too high");
-                Some(path.join(import.to_file_path()))
+                Some((path.join(import.to_file_path()), import, site))
             } else {
                 // resolve absolute (python) imports
                 let path = import.to_file_path();
                 // check first_level_components
+                let name = path.components().next().unwrap().as_os_str();
                 first_level_components
                     .iter()
                     .find(|c| {
-                        c.file_name().unwrap() == path.components().next().unwrap().as_os_str()
+                        // modules are named after their file, without its extension
+                        if c.extension().is_some_and(|ext| ext == PY_EXTENSION) {
+                            c.file_stem() == Some(name)
+                        } else {
+                            c.file_name() == Some(name)
+                        }
                     })
-                    .map(|component| component.parent().unwrap().join(path))
+                    .map(|component| (component.parent().unwrap().join(path), import, site))
             }
         });
 
-        let resolved_imports = imports
-            .filter_map(
-                |import| match determine_import_type(&import, project_files) {
-                    ImportType::Package(p) => Some(p),
-                    ImportType::Module(f) => Some(f),
-                    ImportType::Object => {
-                        snob_debug!("Resolving object import {:?}", import);
-                        match determine_import_type(
-                            import.parent().expect("Import path has no parent"),
-                            project_files,
-                        ) {
-                            ImportType::Package(p) => Some(p),
-                            ImportType::Module(f) => Some(f),
-                            ImportType::Object => {
-                                snob_debug!(
-                                    "Unable to resolve import using crawled files {:?} in file {:?}",
-                                    import.file_name().unwrap(),
-                                    self.file
-                                );
-                                None
-                            }
+        let mut resolved_imports: FxHashMap<String, ImportSite> = FxHashMap::default();
+        let mut unresolved_imports = Vec::new();
+        let resolved = imports.filter_map(|(path, import, site)| {
+            let file = match determine_import_type(&path, project_files) {
+                ImportType::Package(p) => Some(p),
+                ImportType::Module(f) => Some(f),
+                ImportType::Object => {
+                    snob_debug!("Resolving object import {:?}", path);
+                    match determine_import_type(
+                        path.parent().expect("Import path has no parent"),
+                        project_files,
+                    ) {
+                        ImportType::Package(p) => Some(p),
+                        ImportType::Module(f) => Some(f),
+                        ImportType::Object => {
+                            snob_debug!(
+                                "Unable to resolve import using crawled files {:?} in file {:?}",
+                                path.file_name().unwrap(),
+                                self.file
+                            );
+                            unresolved_imports.push(UnresolvedImport {
+                                import: import.to_string(),
+                                site: *site,
+                            });
+                            None
                         }
                     }
-                },
-            )
-            .collect();
+                }
+            };
+            file.map(|file| (file, *site))
+        });
+        for (file, site) in resolved {
+            resolved_imports
+                .entry(file)
+                .and_modify(|first| {
+                    if site.line < first.line {
+                        *first = site;
+                    }
+                })
+                .or_insert(site);
+        }
 
-        resolved_imports
+        (resolved_imports, unresolved_imports)
     }
 }
 
@@ -112,6 +141,16 @@ pub struct Import {
 
 const IMPORT_SEPARATOR: &str = ".";
 
+impl std::fmt::Display for Import {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // from ..a.b import c -> ..a.b.c
+        for _ in 0..self.level {
+            write!(f, "{IMPORT_SEPARATOR}")?;
+        }
+        write!(f, "{}", self.segments.join(IMPORT_SEPARATOR))
+    }
+}
+
 impl Import {
     fn to_file_path(&self) -> PathBuf {
         PathBuf::from(self.segments.join(MAIN_SEPARATOR_STR))
@@ -122,12 +161,82 @@ impl Import {
     }
 }
 
+/// The kind of statement an import comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImportKind {
+    /// `import a.b`
+    Import,
+    /// `from a import b`
+    From,
+    /// `from .a import b`
+    RelativeFrom,
+}
+
+impl std::fmt::Display for ImportKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportKind::Import => write!(f, "import"),
+            ImportKind::From => write!(f, "from"),
+            ImportKind::RelativeFrom => write!(f, "relative from"),
+        }
+    }
+}
+
+/// Where an import is found in the importing file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ImportSite {
+    /// 1-based line of the import statement
+    pub line: usize,
+    pub kind: ImportKind,
+}
+
+/// An edge of the dependency graph, from an imported file to the file importing it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ImportEdge {
+    pub importer: String,
+    pub site: ImportSite,
+}
+
+/// An import that looks like one of the project's, but that no project file matches
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnresolvedImport {
+    /// the imported name (e.g. `app.billing.total` or `..tax`)
+    pub import: String,
+    pub site: ImportSite,
+}
+
+/// Test files that can't be recognized by their name and need to be looked for in the AST
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TestDetection {
+    /// files defining `unittest.TestCase` subclasses
+    pub unittest: bool,
+    /// files with doctest examples in their docstrings (pytest's `--doctest-modules`)
+    pub doctest: bool,
+}
+
+impl TestDetection {
+    fn is_enabled(self) -> bool {
+        self.unittest || self.doctest
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct FileAnalysis {
+    /// imported file -> importing files
+    pub dependencies: FxHashMap<String, Vec<ImportEdge>>,
+    /// whether the file's AST makes it a test target (see `TestDetection`)
+    pub is_test_target: bool,
+    pub unresolved_imports: Vec<UnresolvedImport>,
+}
+
 pub fn extract_file_dependencies(
     file: &PathBuf,
     project_files: &FxHashSet<String>,
     first_level_components: &[PathBuf],
-) -> Result<FxHashMap<String, Vec<String>>> {
-    let file_contents = std::fs::read_to_string(file)?;
+    test_detection: TestDetection,
+    source: &dyn SourceProvider,
+) -> Result<FileAnalysis> {
+    let file_contents = source.read_to_string(file)?;
 
     let mut graph = FxHashMap::default();
 
@@ -135,7 +244,8 @@ pub fn extract_file_dependencies(
         Ok(parsed) => {
             if let Mod::Module(ast) = parsed.syntax() {
                 let mut visitor = ImportVisitor {
-                    imports: FxHashSet::default(),
+                    imports: FxHashMap::default(),
+                    line_starts: line_starts(&file_contents),
                 };
                 visitor.visit_body(&ast.body);
 
@@ -144,17 +254,36 @@ pub fn extract_file_dependencies(
                     imports: visitor.imports.into_iter().collect(),
                 };
 
-                let resolved_imports =
+                let (resolved_imports, unresolved_imports) =
                     file_imports.resolve_imports(project_files, first_level_components);
 
-                for import in resolved_imports {
+                for (import, site) in resolved_imports {
                     graph
                         .entry(import)
                         .or_insert_with(Vec::new)
-                        .push(file.to_string_lossy().to_string());
+                        .push(ImportEdge {
+                            importer: file.to_string_lossy().to_string(),
+                            site,
+                        });
                 }
 
-                Ok(graph)
+                let is_test_target = test_detection.is_enabled() && {
+                    let mut visitor = TestTargetVisitor {
+                        detection: test_detection,
+                        found: has_doctest(test_detection, &ast.body),
+                    };
+                    visitor.visit_body(&ast.body);
+                    visitor.found
+                };
+                if is_test_target {
+                    snob_debug!("{:?} is a test target", file);
+                }
+
+                Ok(FileAnalysis {
+                    dependencies: graph,
+                    is_test_target,
+                    unresolved_imports,
+                })
             } else {
                 anyhow::bail!("Unexpected module type in file {:?}", file);
             }
@@ -165,12 +294,23 @@ pub fn extract_file_dependencies(
 
 #[derive(Debug, Clone)]
 struct ImportVisitor {
-    pub imports: FxHashSet<Import>,
+    /// the first site of each import
+    pub imports: FxHashMap<Import, ImportSite>,
+    /// offsets at which each line starts
+    line_starts: Vec<usize>,
 }
 
 impl ImportVisitor {
+    fn site(&self, offset: usize, kind: ImportKind) -> ImportSite {
+        ImportSite {
+            line: self.line_starts.partition_point(|start| *start <= offset),
+            kind,
+        }
+    }
+
     fn visit_stmt_import(&mut self, stmt: StmtImport) {
         // import a.b.c as c, d.e.f as f
+        let site = self.site(usize::from(stmt.range.start()), ImportKind::Import);
         for alias in stmt.names {
             let import = Import {
                 segments: alias
@@ -180,12 +320,18 @@ impl ImportVisitor {
                     .collect(),
                 level: 0,
             };
-            self.imports.insert(import);
+            self.imports.entry(import).or_insert(site);
         }
     }
 
     fn visit_stmt_import_from(&mut self, stmt: StmtImportFrom) {
         // from ..a.b import c, d
+        let kind = if stmt.level > 0 {
+            ImportKind::RelativeFrom
+        } else {
+            ImportKind::From
+        };
+        let site = self.site(usize::from(stmt.range.start()), kind);
         for alias in stmt.names {
             let mut segments = Vec::new();
             if let Some(module) = &stmt.module {
@@ -205,11 +351,17 @@ impl ImportVisitor {
                 segments,
                 level: stmt.level,
             };
-            self.imports.insert(import);
+            self.imports.entry(import).or_insert(site);
         }
     }
 }
 
+fn line_starts(contents: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(contents.match_indices('\n').map(|(i, _)| i + 1))
+        .collect()
+}
+
 impl StatementVisitor<'_> for ImportVisitor {
     fn visit_stmt(&mut self, stmt: &ruff_python_ast::Stmt) {
         match stmt {
@@ -219,3 +371,63 @@ impl StatementVisitor<'_> for ImportVisitor {
         }
     }
 }
+
+// subclasses of our own TestCase subclasses can't be resolved without looking at other files,
+// so any base class whose name ends with `TestCase` is considered a test case (this also covers
+// `IsolatedAsyncioTestCase` and django's test cases)
+const TEST_CASE_SUFFIX: &str = "TestCase";
+const DOCTEST_PROMPT: &str = ">>>";
+
+#[derive(Debug, Clone)]
+struct TestTargetVisitor {
+    detection: TestDetection,
+    found: bool,
+}
+
+impl StatementVisitor<'_> for TestTargetVisitor {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        if self.found {
+            return;
+        }
+        match stmt {
+            Stmt::ClassDef(class) => {
+                self.found = (self.detection.unittest
+                    && class.bases().iter().any(is_test_case_base))
+                    || has_doctest(self.detection, &class.body);
+            }
+            Stmt::FunctionDef(function) => {
+                self.found = has_doctest(self.detection, &function.body);
+            }
+            _ => {}
+        }
+        walk_stmt(self, stmt);
+    }
+}
+
+fn is_test_case_base(base: &Expr) -> bool {
+    match base {
+        // class MyTests(TestCase)
+        Expr::Name(name) => name.id.ends_with(TEST_CASE_SUFFIX),
+        // class MyTests(unittest.TestCase)
+        Expr::Attribute(attribute) => attribute.attr.ends_with(TEST_CASE_SUFFIX),
+        _ => false,
+    }
+}
+
+// a docstring is the string literal a module, class or function body starts with
+fn has_doctest(detection: TestDetection, body: &[Stmt]) -> bool {
+    if !detection.doctest {
+        return false;
+    }
+    match body.first() {
+        Some(Stmt::Expr(stmt)) => match stmt.value.as_ref() {
+            Expr::StringLiteral(docstring) => docstring
+                .value
+                .to_str()
+                .lines()
+                .any(|line| line.trim_start().starts_with(DOCTEST_PROMPT)),
+            _ => false,
+        },
+        _ => false,
+    }
+}
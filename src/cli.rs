@@ -1,14 +1,24 @@
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
+
+use crate::export::GraphFormat;
+use crate::git::ChangeSelection;
+use crate::stdin::InputFormat;
+
+/// The changed file argument standing for stdin
+pub const STDIN_ARG: &str = "-";
 
 #[derive(Parser, Debug)]
 #[command(
     version,
     about = "Find tests impacted by code changes",
-    long_about = "Snob analyzes your Python code changes and identifies which tests need to run.\n\nUsage examples:\n  git diff --name-only | snob | xargs pytest\n  snob src/auth.py src/utils.py\n  git diff --name-only HEAD~1..HEAD | snob | xargs pytest"
+    long_about = "Snob analyzes your Python code changes and identifies which tests need to run.\n\nUsage examples:\n  git diff --name-only | snob - | xargs pytest\n  git diff -z --name-status main | snob -z --name-status - | xargs pytest\n  snob src/auth.py src/utils.py\n  snob --base main --staged --unstaged --untracked | xargs pytest\n  git diff --name-only HEAD~1..HEAD | snob - | xargs pytest"
 )]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// The target directory to analyze for dependencies
     #[arg(short, long, value_name = "DIR", default_value = ".")]
     pub target_directory: PathBuf,
@@ -18,25 +28,268 @@ pub struct Cli {
     #[arg(
         value_name = "CHANGED_FILES",
         help = "Python files that were modified and need testing.
-These can easily be obtained using your version control system (e.g., `git diff --name-only`)"
+These can easily be obtained using your version control system (e.g., `git diff --name-only`)
+Use `-` to read them from stdin"
     )]
     pub updated_files: Vec<String>,
 
+    /// Read the changed files from stdin (one per line), in addition to the ones passed as
+    /// arguments (same as `-`)
+    #[arg(long, default_value = "false", conflicts_with_all = ["base", "staged", "unstaged", "untracked"])]
+    pub stdin: bool,
+
+    /// The changed files read from stdin are separated by NUL characters
+    /// (e.g. `git diff --name-only -z`) and are taken as is
+    #[arg(short = 'z', long = "null", default_value = "false")]
+    pub nul_separated: bool,
+
+    /// The changed files read from stdin come from `git diff --name-status`: deleted files
+    /// and the old paths of renamed ones are known to be gone
+    #[arg(long, default_value = "false")]
+    pub name_status: bool,
+
+    /// Select the files changed since the merge base of this revision and HEAD
+    /// Can be combined with `--staged`, `--unstaged` and `--untracked`
+    #[arg(long, value_name = "REV", conflicts_with = "updated_files")]
+    pub base: Option<String>,
+
+    /// Build the dependency graph from the files of this revision (read through git) instead of
+    /// the working tree, `--base` is then compared to this revision instead of HEAD
+    #[arg(long, value_name = "REV", conflicts_with_all = ["staged", "unstaged", "untracked"])]
+    pub revision: Option<String>,
+
+    /// Also build the dependency graph at the merge base of `--base` and traverse the union of
+    /// both graphs, so that imports removed by the changes are considered as well
+    #[arg(long, default_value = "false", requires = "base")]
+    pub include_base_graph: bool,
+
+    /// Select the files with changes added to the index
+    #[arg(long, default_value = "false", conflicts_with = "updated_files")]
+    pub staged: bool,
+
+    /// Select the files with changes not added to the index
+    #[arg(long, default_value = "false", conflicts_with = "updated_files")]
+    pub unstaged: bool,
+
+    /// Select the files that are neither tracked nor ignored
+    #[arg(long, default_value = "false", conflicts_with = "updated_files")]
+    pub untracked: bool,
+
     /// Verbosity level (0-4+)
     /// 0 -> ERROR
     /// 1 -> WARN
     /// 2 -> INFO
     /// 3 -> DEBUG
     /// 4 or higher -> TRACE
-    #[arg(short, long, default_value = "2")]
+    #[arg(short, long, default_value = "2", global = true)]
     pub verbosity_level: usize,
 
     /// Quiet mode
-    #[arg(short, long, default_value = "false")]
+    #[arg(short, long, default_value = "false", global = true)]
     pub quiet: bool,
 
-    /// Produce DOT graph at provided path
+    /// Produce DOT graph of the impacted files at provided path, clustered by package
     /// see https://graphviz.org/doc/info/lang.html
     #[arg(short, long, value_name = "FILE")]
     pub dot_graph: Option<PathBuf>,
+
+    /// Draw the packages of the DOT graph holding more than N files as a single node
+    #[arg(long, value_name = "N", requires = "dot_graph")]
+    pub dot_collapse_threshold: Option<usize>,
+
+    /// Export the whole dependency graph at provided path, along with the attributes of each file
+    /// (module, package, is_test, is_changed, parse_failed)
+    #[arg(long, value_name = "FILE")]
+    pub export_graph: Option<PathBuf>,
+
+    /// Format of the exported graph, guessed from the extension of its file by default
+    /// (`.json`, `.graphml`, `.mmd` or `.mermaid`)
+    #[arg(long, value_enum, requires = "export_graph")]
+    pub export_format: Option<ExportFormat>,
+
+    /// Only export the files impacted by the changes and the imports between them
+    #[arg(long, default_value = "false", requires = "export_graph")]
+    pub export_impacted_only: bool,
+
+    /// Configuration file to use instead of the one found at the repository root
+    /// (configuration files found in subdirectories are still merged into it)
+    #[arg(short, long, value_name = "FILE", global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Project root, instead of the closest directory containing one of the root markers
+    /// (`.git`, `.hg`, `.jj`, `snob.toml` or `pyproject.toml`, see `SNOB_ROOT_MARKERS`)
+    #[arg(long, value_name = "DIR", global = true)]
+    pub root: Option<PathBuf>,
+
+    /// Python interpreter to query for `sys.path` instead of inferring lookup paths
+    /// from the working directory and PYTHONPATH (results are cached)
+    #[arg(long, value_name = "INTERPRETER")]
+    pub python: Option<PathBuf>,
+
+    /// Group impacted tests by uv / Poetry workspace member
+    /// Each output line holds the member directory (relative to the repository root)
+    /// and a test path relative to that directory, separated by a tab
+    #[arg(long, default_value = "false", conflicts_with = "format")]
+    pub group_by_member: bool,
+
+    /// Output format: one test per line, or a JSON object describing the selection
+    /// (mode, impacted / always run / ignored tests, changed files and why each test was selected)
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+
+    /// What to print: the tests to run, or every impacted python file (changed files included),
+    /// e.g. to run type checkers and linters on the impacted code only
+    #[arg(long, value_enum, default_value_t = Emit::Tests)]
+    pub emit: Emit,
+
+    /// Leave the test files out of the emitted modules (requires `--emit modules`)
+    #[arg(long, default_value = "false")]
+    pub exclude_tests: bool,
+
+    /// Only emit the modules matching this glob (relative to the project root), can be repeated
+    /// (requires `--emit modules`)
+    #[arg(long, value_name = "GLOB")]
+    pub modules_glob: Vec<String>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Graphml,
+    Mermaid,
+}
+
+impl From<ExportFormat> for GraphFormat {
+    fn from(format: ExportFormat) -> Self {
+        match format {
+            ExportFormat::Json => Self::Json,
+            ExportFormat::Graphml => Self::GraphMl,
+            ExportFormat::Mermaid => Self::Mermaid,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Emit {
+    /// the tests to run
+    Tests,
+    /// the impacted python files
+    Modules,
+}
+
+impl Cli {
+    pub fn change_selection(&self) -> ChangeSelection {
+        ChangeSelection {
+            base: self.base.clone(),
+            staged: self.staged,
+            unstaged: self.unstaged,
+            untracked: self.untracked,
+            revision: self.revision.clone(),
+        }
+    }
+
+    /// Whether the changed files should be read from stdin (`-` or `--stdin`)
+    pub fn reads_stdin(&self) -> bool {
+        self.stdin || self.updated_files.iter().any(|f| f == STDIN_ARG)
+    }
+
+    pub fn input_format(&self) -> InputFormat {
+        InputFormat {
+            nul_separated: self.nul_separated,
+            name_status: self.name_status,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Inspect snob's configuration
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Show the changed files and impacted tests of each commit of a revision range, along with
+    /// the union of their impacted tests
+    Commits {
+        /// The revision range to inspect (e.g. `main..HEAD`)
+        #[arg(value_name = "RANGE")]
+        range: String,
+    },
+    /// List the commits of a revision range that modified a test or any file it depends on,
+    /// to narrow down a bisect
+    BlameRange {
+        /// The test file
+        #[arg(value_name = "TEST")]
+        test: PathBuf,
+        /// The revision range to inspect (e.g. `v1.2..HEAD`)
+        #[arg(value_name = "RANGE")]
+        range: String,
+    },
+    /// Explain why a test is selected: print the shortest chain of imports from each changed
+    /// file it depends on, with the line of every import statement
+    Why {
+        /// The test file
+        #[arg(value_name = "TEST")]
+        test: PathBuf,
+        /// The changed files (the uncommitted changes of the repository by default)
+        #[arg(value_name = "CHANGED_FILES")]
+        changed_files: Vec<String>,
+    },
+    /// List the files a file imports, directly or not
+    Deps {
+        /// The python file
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+        /// Only follow this many levels of imports (1 for direct imports)
+        #[arg(long, value_name = "N")]
+        depth: Option<usize>,
+    },
+    /// List the files importing a file, directly or not, tests or not
+    Rdeps {
+        /// The python file
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+        /// Only follow this many levels of imports (1 for direct imports)
+        #[arg(long, value_name = "N")]
+        depth: Option<usize>,
+    },
+    /// Write an interactive HTML report of the impacted files and selected tests, to review the
+    /// selection of a change
+    Report {
+        /// The HTML file to write (a single page, without any network resource)
+        #[arg(long, value_name = "FILE")]
+        html: PathBuf,
+        /// The changed files (the uncommitted changes of the repository by default)
+        #[arg(value_name = "CHANGED_FILES")]
+        changed_files: Vec<String>,
+    },
+    /// List the import cycles of the project, with the import statements forming them
+    Cycles {
+        /// File of known cycles: fail when a cycle is not part of it
+        #[arg(long, value_name = "FILE")]
+        baseline: Option<PathBuf>,
+        /// Write the current cycles to the baseline file instead of checking them
+        #[arg(long, default_value = "false", requires = "baseline")]
+        update_baseline: bool,
+    },
+    /// Explain why a test is not selected: whether it depends on the changed files at all, and
+    /// the ignored files, unresolved imports and parse failures found among its dependencies
+    WhyNot {
+        /// The test file
+        #[arg(value_name = "TEST")]
+        test: PathBuf,
+        /// The changed files (the uncommitted changes of the repository by default)
+        #[arg(value_name = "CHANGED_FILES")]
+        changed_files: Vec<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Validate the configuration files and print the effective (merged) configuration
+    Check,
 }
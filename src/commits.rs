@@ -0,0 +1,221 @@
+use anyhow::Result;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use crate::analysis::{Analysis, Layout};
+use crate::config::Config;
+use crate::git::{parse_name_status, run_git, EMPTY_TREE};
+use crate::pytest::TestFileMatcher;
+use crate::results::SnobResult;
+use crate::source::{GitRevision, SourceProvider, WorkingTree};
+use crate::{fs, graph, snob_debug, utils};
+
+/// The changes of a single commit and the tests they impact
+#[derive(Debug)]
+pub struct CommitImpact {
+    pub commit: String,
+    pub summary: String,
+    /// absolute paths
+    pub changed: Vec<String>,
+    /// absolute paths, `None` when a changed file triggers all tests
+    pub impacted: Option<BTreeSet<String>>,
+}
+
+/// Compute the impacted tests of every commit of `range` (e.g. `main..HEAD`), oldest first.
+///
+/// Each commit is compared to its first parent, and its graph is built from its own tree.
+/// Commits that don't modify any python file reuse the graph of the previous one.
+pub fn impacted_tests_per_commit(
+    config: &Config,
+    layout: &Layout,
+    git_root: &Path,
+    range: &str,
+) -> Result<Vec<CommitImpact>> {
+    let run_all_tests_on_change = fs::build_glob_set(&config.files.run_all_tests_on_change)?;
+    let ignored_tests = fs::build_glob_set(&config.tests.ignores)?;
+    let tests_to_always_run = fs::build_glob_set(&config.tests.always_run)?;
+
+    let mut blob_cache = FxHashMap::default();
+    let mut previous: Option<(GitRevision, Analysis)> = None;
+    let mut impacts = Vec::new();
+
+    for (commit, parent, summary) in list_commits(git_root, range)? {
+        let changed = parse_name_status(&run_git(
+            git_root,
+            &[
+                "diff",
+                "-z",
+                "--name-status",
+                "-M",
+                // `git_root` may be below the git toplevel (see `git::get_changed_files`)
+                "--relative",
+                parent.as_deref().unwrap_or(EMPTY_TREE),
+                &commit,
+            ],
+        )?)
+        .into_iter()
+        .map(|f| git_root.join(f).to_string_lossy().to_string())
+        .collect::<FxHashSet<String>>();
+
+        if utils::should_run_all_tests(&changed, &run_all_tests_on_change, git_root) {
+            impacts.push(CommitImpact {
+                commit,
+                summary,
+                changed: sorted(changed),
+                impacted: None,
+            });
+            continue;
+        }
+
+        let revision = GitRevision::with_blob_cache(git_root, &commit, &mut blob_cache)?;
+        let (revision, analysis) = match previous.take() {
+            Some((previous_revision, analysis)) if previous_revision.key() == revision.key() => {
+                snob_debug!("Reusing the graph of the previous commit for {}", commit);
+                (previous_revision, analysis)
+            }
+            _ => {
+                let deleted_files = fs::find_deleted_files(&changed, &revision);
+                let analysis =
+                    Analysis::new(&revision, layout, config, git_root, &deleted_files, None)?;
+                (revision, analysis)
+            }
+        };
+
+        let mut impacted_nodes =
+            graph::discover_impacted_nodes(&analysis.dependency_graph, &changed);
+        impacted_nodes.retain(|f| revision.exists(Path::new(f)));
+        let test_matcher = TestFileMatcher::new(config, git_root)?
+            .with_test_targets(analysis.test_targets.clone());
+        let snob_results = SnobResult::new(
+            impacted_nodes,
+            analysis.project_files.clone(),
+            &ignored_tests,
+            &tests_to_always_run,
+            &test_matcher,
+            git_root,
+        );
+
+        impacts.push(CommitImpact {
+            commit,
+            summary,
+            changed: sorted(changed),
+            impacted: Some(snob_results.impacted.into_iter().collect()),
+        });
+        previous = Some((revision, analysis));
+    }
+
+    Ok(impacts)
+}
+
+/// The tests impacted by any of the commits, `None` when one of them triggers all tests
+pub fn union(impacts: &[CommitImpact]) -> Option<BTreeSet<String>> {
+    impacts
+        .iter()
+        .try_fold(BTreeSet::new(), |mut union, impact| {
+            union.extend(impact.impacted.as_ref()?.iter().cloned());
+            Some(union)
+        })
+}
+
+/// A commit that modified files of a test's dependency closure
+#[derive(Debug)]
+pub struct ClosureCommit {
+    pub commit: String,
+    pub summary: String,
+    /// the files of the closure it modified, absolute paths
+    pub touched: Vec<String>,
+}
+
+/// List the commits of `range` that modified the test or any of the files it depends on
+/// (directly or not), oldest first: the candidates when bisecting a failure of that test.
+///
+/// The closure is computed on the working tree, so files renamed within the range are only
+/// followed under their current name.
+pub fn commits_touching_test(
+    config: &Config,
+    layout: &Layout,
+    git_root: &Path,
+    test: &str,
+    range: &str,
+) -> Result<Vec<ClosureCommit>> {
+    let analysis = Analysis::new(
+        &WorkingTree,
+        layout,
+        config,
+        git_root,
+        &FxHashSet::default(),
+        None,
+    )?;
+    if !analysis.project_files.contains(test) {
+        anyhow::bail!("{test} is not part of the analyzed files");
+    }
+    let closure = graph::discover_dependencies(
+        &analysis.dependency_graph,
+        &FxHashSet::from_iter([test.to_string()]),
+    );
+    snob_debug!("Dependency closure of {}: {:?}", test, closure);
+
+    // the closure may not fit on the command line as pathspecs (which `git log` can't read
+    // from a file): the whole range is listed, then filtered
+    let output = run_git(
+        git_root,
+        &[
+            "log",
+            "-z",
+            "--reverse",
+            "--name-only",
+            // `git_root` may be below the git toplevel (see `git::get_changed_files`)
+            "--relative",
+            // \x01 tells commit headers apart from file names
+            "--format=%x01%H %s",
+            range,
+        ],
+    )?;
+
+    // \x01<commit> SP <subject> NUL LF <file> NUL <file> NUL ...
+    let mut commits: Vec<ClosureCommit> = Vec::new();
+    for token in output.split('\0').filter(|t| !t.is_empty()) {
+        if let Some(header) = token.strip_prefix('\x01') {
+            let (commit, summary) = header.split_once(' ').unwrap_or((header, ""));
+            commits.push(ClosureCommit {
+                commit: commit.to_string(),
+                summary: summary.to_string(),
+                touched: Vec::new(),
+            });
+        } else if let Some(current) = commits.last_mut() {
+            let file = token.strip_prefix('\n').unwrap_or(token);
+            let file = git_root.join(file).to_string_lossy().to_string();
+            if closure.contains(&file) {
+                current.touched.push(file);
+            }
+        }
+    }
+    commits.retain(|commit| !commit.touched.is_empty());
+    Ok(commits)
+}
+
+// (commit, first parent, subject), oldest first
+fn list_commits(git_root: &Path, range: &str) -> Result<Vec<(String, Option<String>, String)>> {
+    let output = run_git(
+        git_root,
+        &["log", "-z", "--reverse", "--format=%H %P%n%s", range],
+    )?;
+    Ok(output
+        .split('\0')
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let (hashes, summary) = entry.split_once('\n').unwrap_or((entry, ""));
+            let mut hashes = hashes.split(' ');
+            let commit = hashes.next()?.to_string();
+            let parent = hashes.next().filter(|p| !p.is_empty()).map(String::from);
+            Some((commit, parent, summary.to_string()))
+        })
+        .collect())
+}
+
+fn sorted(files: FxHashSet<String>) -> Vec<String> {
+    let mut files = files.into_iter().collect::<Vec<_>>();
+    files.sort();
+    files
+}
@@ -1,11 +1,20 @@
+use globset::Glob;
+use ignore::{types::TypesBuilder, WalkBuilder};
+#[cfg(feature = "python")]
+use pyo3::{exceptions::PyValueError, PyErr};
 use rustc_hash::FxHashSet;
-use std::path::Path;
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize, Serializer};
 
-use crate::snob_debug;
+use crate::ast::TestDetection;
+use crate::utils;
+use crate::{snob_debug, snob_warn};
 
-#[derive(Debug, Default, Deserialize)]
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
     /// general configuration (used by Python interface)
     #[serde(default)]
@@ -17,10 +26,14 @@ pub struct Config {
     /// tests-related configuration
     #[serde(default)]
     pub tests: TestsConfig,
+    /// configuration files this configuration was read from
+    #[serde(skip)]
+    pub sources: Vec<PathBuf>,
 }
 
 // Used by Python interface in lib.rs for logging configuration
-#[derive(Debug, Default, Deserialize)]
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 pub struct GeneralConfig {
     #[serde(default = "default_verbosity_level")]
     #[allow(dead_code)] // Used by PyO3 interface in lib.rs
@@ -38,51 +51,413 @@ fn default_quiet() -> bool {
     false
 }
 
-#[derive(Debug, Default, Deserialize)]
-#[serde(rename_all = "kebab-case")]
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub struct FilesConfig {
     /// the files listed here will be ignored by snob when crawling the workspace
-    #[serde(default)]
+    #[serde(default, serialize_with = "serialize_sorted")]
     pub ignores: FxHashSet<String>,
     /// the files listed here will trigger all tests on change
-    #[serde(default)]
+    #[serde(default, serialize_with = "serialize_sorted")]
     pub run_all_tests_on_change: FxHashSet<String>,
 }
 
-#[derive(Debug, Default, Deserialize)]
-#[serde(rename_all = "kebab-case")]
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub struct TestsConfig {
     /// the tests listed here will always run
-    #[serde(default)]
+    #[serde(default, serialize_with = "serialize_sorted")]
     pub always_run: FxHashSet<String>,
     /// the tests listed here will never run
-    #[serde(default)]
+    #[serde(default, serialize_with = "serialize_sorted")]
     pub ignores: FxHashSet<String>,
+    /// file name patterns of test files (overrides pytest's `python_files`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub python_files: Option<Vec<String>>,
+    /// directories containing the tests (overrides pytest's `testpaths`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub testpaths: Option<Vec<String>>,
+    /// directory name patterns that never contain tests (overrides pytest's `norecursedirs`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub norecursedirs: Option<Vec<String>>,
+    /// also consider files defining `unittest.TestCase` subclasses as tests
+    #[serde(default)]
+    pub unittest_test_cases: bool,
+    /// also consider files containing doctests as tests (pytest's `--doctest-modules`)
+    #[serde(default)]
+    pub doctest_modules: bool,
+    /// the two settings above, as set by nested configuration files: they only apply to the
+    /// files below their directory (relative to the repository root)
+    #[serde(skip)]
+    pub nested_test_detection: Vec<(PathBuf, TestDetection)>,
+    /// `testpaths` set by nested configuration files, relative to the repository root: they
+    /// replace the parents' ones for the files below their directory
+    #[serde(skip)]
+    pub nested_testpaths: Vec<(PathBuf, Vec<PathBuf>)>,
+}
+
+impl TestsConfig {
+    /// How to recognize `relative_file` (relative to the repository root) as a test from its AST
+    pub fn test_detection(&self, relative_file: &Path) -> TestDetection {
+        self.nested_test_detection
+            .iter()
+            .filter(|(dir, _)| relative_file.starts_with(dir))
+            .fold(
+                TestDetection {
+                    unittest: self.unittest_test_cases,
+                    doctest: self.doctest_modules,
+                },
+                |detection, (_, nested)| TestDetection {
+                    unittest: detection.unittest || nested.unittest,
+                    doctest: detection.doctest || nested.doctest,
+                },
+            )
+    }
+}
+
+// keeps the output of `snob config check` stable
+fn serialize_sorted<S>(set: &FxHashSet<String>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    set.iter().collect::<BTreeSet<_>>().serialize(serializer)
+}
+
+/// An invalid configuration file
+#[derive(Debug)]
+pub struct ConfigError {
+    pub path: PathBuf,
+    /// 1-based line and column of the error in the file, when known
+    pub location: Option<(usize, usize)>,
+    /// the (dotted) key the error relates to, when known
+    pub key: Option<String>,
+    pub message: String,
+}
+
+impl ConfigError {
+    fn new(path: &Path, message: impl Into<String>) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            location: None,
+            key: None,
+            message: message.into(),
+        }
+    }
+
+    fn from_toml(path: &Path, contents: &str, error: &toml::de::Error) -> Self {
+        let offset = error.span().map(|span| span.start);
+        Self {
+            path: path.to_path_buf(),
+            location: offset.map(|o| line_and_column(contents, o)),
+            key: offset.and_then(|o| key_at(contents, o)),
+            message: error.message().to_string(),
+        }
+    }
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid configuration file {}", self.path.display())?;
+        if let Some((line, column)) = self.location {
+            write!(f, ":{line}:{column}")?;
+        }
+        if let Some(key) = &self.key {
+            write!(f, " (key `{key}`)")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[cfg(feature = "python")]
+impl std::convert::From<ConfigError> for PyErr {
+    fn from(err: ConfigError) -> PyErr {
+        PyValueError::new_err(err.to_string())
+    }
+}
+
+fn line_and_column(contents: &str, offset: usize) -> (usize, usize) {
+    let before = &contents[..offset.min(contents.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+    (line, column)
+}
+
+// best effort reconstruction of the dotted key found at `offset`, using the last table header
+// and the key on the offending line
+fn key_at(contents: &str, offset: usize) -> Option<String> {
+    let (line, _) = line_and_column(contents, offset);
+    let lines = contents.lines().take(line).collect::<Vec<_>>();
+    let current = lines.last()?.trim();
+    let header = |l: &str| {
+        l.trim()
+            .strip_prefix('[')
+            .map(|h| h.trim_matches(|c| c == '[' || c == ']').trim().to_string())
+    };
+    if let Some(table) = header(current) {
+        return Some(table);
+    }
+    let key = current.split_once('=')?.0.trim();
+    match lines.iter().rev().skip(1).find_map(|l| header(l)) {
+        Some(table) => Some(format!("{table}.{key}")),
+        None => Some(key.to_string()),
+    }
+}
+
+// mirrors `[tool.snob]` in `pyproject.toml`, other tools' sections are left alone
+#[derive(Deserialize)]
+struct PyProject {
+    tool: Option<PyProjectTools>,
+}
+
+#[derive(Deserialize)]
+struct PyProjectTools {
+    snob: Option<Config>,
 }
 
 const CONFIG_FILE_SNOB: &str = "snob.toml";
 const CONFIG_FILE_PYPROJECT: &str = "pyproject.toml";
 
 impl Config {
-    pub fn new(git_root: &Path) -> Self {
-        // try to find the config file at `$GIT_ROOT/snob.toml`
-        if let Ok(config) = std::fs::read_to_string(git_root.join(CONFIG_FILE_SNOB)) {
-            snob_debug!("Found config file at {:?}", git_root.join(CONFIG_FILE_SNOB));
-            return toml::from_str(&config).unwrap();
-        // if it doesn't exist, check `$GIT_ROOT/pyproject.toml`
-        } else if let Ok(config) = std::fs::read_to_string(git_root.join(CONFIG_FILE_PYPROJECT)) {
-            snob_debug!(
-                "Found config file at {:?}",
-                git_root.join(CONFIG_FILE_PYPROJECT)
-            );
-            let pyproject: toml::Value = toml::from_str(&config).unwrap();
-            if let Some(tool) = pyproject.get("tool") {
-                if let Some(snob) = tool.get("snob") {
-                    return snob.clone().try_into().unwrap();
+    /// Load the configuration of the repository: the root configuration file merged with the
+    /// configuration files found in its subdirectories
+    #[allow(dead_code)] // Used by PyO3 interface in lib.rs
+    pub fn new(git_root: &Path) -> Result<Self, ConfigError> {
+        Self::load(git_root, None)
+    }
+
+    /// Same as `Config::new`, but the root configuration is read from `config_file` (if provided)
+    /// instead of being looked up at `$GIT_ROOT`.
+    /// Globs of the root configuration are relative to the repository root in both cases.
+    pub fn load(git_root: &Path, config_file: Option<&Path>) -> Result<Self, ConfigError> {
+        let mut config = match config_file {
+            Some(config_file) => {
+                snob_debug!("Using config file at {:?}", config_file);
+                if !config_file.is_file() {
+                    return Err(ConfigError::new(config_file, "file not found"));
+                }
+                read_config_file(config_file, false)?.unwrap_or_default()
+            }
+            None => read_config_dir(git_root, false)?.unwrap_or_default(),
+        };
+
+        // an explicit config file living in the repository is not a nested config as well
+        let config_dir = config_file
+            .and_then(|f| std::path::absolute(f).ok())
+            .and_then(|f| utils::normalize_path(&f).parent().map(Path::to_path_buf));
+        for dir in find_nested_config_dirs(git_root) {
+            if Some(&dir) == config_dir.as_ref() {
+                continue;
+            }
+            if let Some(nested) = read_config_dir(&dir, true)? {
+                config.merge_nested(nested, dir.strip_prefix(git_root).unwrap());
+            }
+        }
+        Ok(config)
+    }
+
+    /// Merge the configuration found in a subdirectory of the repository.
+    /// Its globs are relative to its own directory, so they get prefixed with `relative_dir`,
+    /// and its settings add up to the parents' ones (general settings are only read from the
+    /// root configuration)
+    fn merge_nested(&mut self, nested: Config, relative_dir: &Path) {
+        let prefix = globset::escape(&relative_dir.to_string_lossy());
+        let rebase = |globs: FxHashSet<String>| {
+            globs
+                .into_iter()
+                .map(|g| format!("{prefix}/{}", g.trim_start_matches("./")))
+                .collect::<Vec<_>>()
+        };
+        self.files.ignores.extend(rebase(nested.files.ignores));
+        self.files
+            .run_all_tests_on_change
+            .extend(rebase(nested.files.run_all_tests_on_change));
+        self.tests
+            .always_run
+            .extend(rebase(nested.tests.always_run));
+        self.tests.ignores.extend(rebase(nested.tests.ignores));
+        // test discovery settings only apply below the nested config
+        // (`python-files` and `norecursedirs` are rejected in nested configs, see `validate_nested`)
+        let detection = TestDetection {
+            unittest: nested.tests.unittest_test_cases,
+            doctest: nested.tests.doctest_modules,
+        };
+        if detection.unittest || detection.doctest {
+            self.tests
+                .nested_test_detection
+                .push((relative_dir.to_path_buf(), detection));
+        }
+        if let Some(testpaths) = nested.tests.testpaths {
+            self.tests.nested_testpaths.push((
+                relative_dir.to_path_buf(),
+                testpaths.iter().map(|p| relative_dir.join(p)).collect(),
+            ));
+        }
+        self.sources.extend(nested.sources);
+    }
+
+    /// Render the configuration as TOML, listing the files it was merged from
+    pub fn to_toml(&self) -> anyhow::Result<String> {
+        let sources = self
+            .sources
+            .iter()
+            .map(|s| format!("# - {}\n", s.display()))
+            .collect::<String>();
+        let header = if sources.is_empty() {
+            "# no configuration file found, using defaults\n".to_string()
+        } else {
+            format!("# merged from:\n{sources}")
+        };
+        Ok(format!("{header}\n{}", toml::to_string_pretty(self)?))
+    }
+
+    // like pytest's own settings, the file name patterns of test files apply to the whole
+    // repository: they are only read from the root configuration
+    fn validate_nested(
+        &self,
+        path: &Path,
+        contents: &str,
+        key_prefix: &str,
+    ) -> Result<(), ConfigError> {
+        let root_only = [
+            ("python-files", self.tests.python_files.is_some()),
+            ("norecursedirs", self.tests.norecursedirs.is_some()),
+        ];
+        match root_only.into_iter().find(|(_, is_set)| *is_set) {
+            Some((key, _)) => Err(ConfigError {
+                path: path.to_path_buf(),
+                location: contents
+                    .rfind(&format!("\n{key}"))
+                    .map(|o| line_and_column(contents, o + 1)),
+                key: Some(format!("{key_prefix}tests.{key}")),
+                message: format!(
+                    "`{key}` applies to the whole repository and can only be set in the root \
+                     configuration"
+                ),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    // globs are compiled much later in the run, fail early and point at the culprit instead
+    fn validate_globs(
+        &self,
+        path: &Path,
+        contents: &str,
+        key_prefix: &str,
+    ) -> Result<(), ConfigError> {
+        let globs = [
+            ("files.ignores", &self.files.ignores),
+            (
+                "files.run-all-tests-on-change",
+                &self.files.run_all_tests_on_change,
+            ),
+            ("tests.always-run", &self.tests.always_run),
+            ("tests.ignores", &self.tests.ignores),
+        ];
+        for (key, globs) in globs {
+            for glob in globs {
+                if let Err(e) = Glob::new(glob) {
+                    return Err(ConfigError {
+                        path: path.to_path_buf(),
+                        location: contents
+                            .find(&format!("\"{glob}\""))
+                            .or_else(|| contents.find(&format!("'{glob}'")))
+                            .map(|o| line_and_column(contents, o + 1)),
+                        key: Some(format!("{key_prefix}{key}")),
+                        message: format!("invalid glob {glob:?}: {}", e.kind()),
+                    });
                 }
             }
         }
-        // if it doesn't exist, use the default config
-        Self::default()
+        Ok(())
+    }
+}
+
+// `snob.toml` takes precedence over `pyproject.toml` within the same directory
+fn read_config_dir(dir: &Path, nested: bool) -> Result<Option<Config>, ConfigError> {
+    match read_config_file(&dir.join(CONFIG_FILE_SNOB), nested)? {
+        Some(config) => Ok(Some(config)),
+        None => read_config_file(&dir.join(CONFIG_FILE_PYPROJECT), nested),
+    }
+}
+
+// returns `None` if the file does not exist, or is a `pyproject.toml` without a snob section.
+// Nested `pyproject.toml` files belong to other packages first: the ones that are not valid TOML
+// are skipped with a warning instead of failing the whole run
+fn read_config_file(config_file: &Path, nested: bool) -> Result<Option<Config>, ConfigError> {
+    let contents = match std::fs::read_to_string(config_file) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(ConfigError::new(config_file, e.to_string())),
+    };
+    snob_debug!("Found config file at {:?}", config_file);
+
+    let is_pyproject = config_file
+        .file_name()
+        .is_some_and(|f| f == CONFIG_FILE_PYPROJECT);
+    let (config, key_prefix) = if is_pyproject {
+        let table = match toml::from_str::<toml::Table>(&contents) {
+            Ok(table) => table,
+            Err(e) if nested => {
+                snob_warn!(
+                    "Skipping {}, not a valid TOML file: {}",
+                    config_file.display(),
+                    e.message()
+                );
+                return Ok(None);
+            }
+            Err(e) => return Err(ConfigError::from_toml(config_file, &contents, &e)),
+        };
+        // only the snob section is validated, other tools' sections are left alone
+        if table
+            .get("tool")
+            .and_then(|tool| tool.get("snob"))
+            .is_none()
+        {
+            return Ok(None);
+        }
+        let pyproject: PyProject = toml::from_str(&contents)
+            .map_err(|e| ConfigError::from_toml(config_file, &contents, &e))?;
+        match pyproject.tool.and_then(|t| t.snob) {
+            Some(config) => (config, "tool.snob."),
+            None => return Ok(None),
+        }
+    } else {
+        let config: Config = toml::from_str(&contents)
+            .map_err(|e| ConfigError::from_toml(config_file, &contents, &e))?;
+        (config, "")
+    };
+    config.validate_globs(config_file, &contents, key_prefix)?;
+    if nested {
+        config.validate_nested(config_file, &contents, key_prefix)?;
     }
+
+    Ok(Some(Config {
+        sources: vec![config_file.to_path_buf()],
+        ..config
+    }))
+}
+
+// directories below the repository root that contain a configuration file
+fn find_nested_config_dirs(git_root: &Path) -> Vec<PathBuf> {
+    let mut types_builder = TypesBuilder::new();
+    types_builder
+        .add("snob", CONFIG_FILE_SNOB)
+        .and_then(|()| types_builder.add("snob", CONFIG_FILE_PYPROJECT))
+        .unwrap();
+    types_builder.select("snob");
+
+    let mut dirs = WalkBuilder::new(git_root)
+        .types(types_builder.build().unwrap())
+        .build()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .filter_map(|entry| entry.path().parent().map(Path::to_path_buf))
+        .filter(|dir| dir != git_root)
+        .collect::<Vec<_>>();
+    dirs.sort();
+    dirs.dedup();
+    dirs
 }
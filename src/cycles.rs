@@ -0,0 +1,95 @@
+use anyhow::{Context, Result};
+use rustc_hash::FxHashSet;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use crate::analysis::Analysis;
+use crate::ast::ImportSite;
+use crate::{graph, utils};
+
+/// Files importing each other, directly or not
+#[derive(Debug, PartialEq, Eq)]
+pub struct ImportCycle {
+    /// absolute paths, sorted
+    pub files: Vec<String>,
+    /// the imports between the files of the cycle: (importing file, imported file, statement),
+    /// sorted
+    pub imports: Vec<(String, String, ImportSite)>,
+}
+
+/// Find the import cycles of the analyzed files (see `graph::import_cycles`)
+pub fn find_import_cycles(analysis: &Analysis) -> Vec<ImportCycle> {
+    graph::import_cycles(&analysis.dependency_graph)
+        .into_iter()
+        .map(|files| {
+            let members = files.iter().collect::<FxHashSet<_>>();
+            let mut imports = files
+                .iter()
+                .flat_map(|imported| {
+                    analysis
+                        .import_edges
+                        .get(imported)
+                        .into_iter()
+                        .flatten()
+                        .filter(|edge| members.contains(&edge.importer))
+                        .map(move |edge| (edge.importer.clone(), imported.clone(), edge.site))
+                })
+                .collect::<Vec<_>>();
+            imports.sort_by(|(a, a_imported, a_site), (b, b_imported, b_site)| {
+                (a, a_site.line, a_imported).cmp(&(b, b_site.line, b_imported))
+            });
+            ImportCycle { files, imports }
+        })
+        .collect()
+}
+
+/// Read a baseline of known cycles: one cycle per line, made of the tab separated paths of its
+/// files (relative to the project root). Empty lines and lines starting with `#` are skipped.
+pub fn read_baseline(path: &Path) -> Result<Vec<BTreeSet<String>>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read the cycles baseline {}", path.display()))?;
+    Ok(content
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|line| line.split('\t').map(ToString::to_string).collect())
+        .collect())
+}
+
+/// Write `cycles` as a baseline (see `read_baseline`)
+pub fn write_baseline(path: &Path, cycles: &[ImportCycle], git_root: &Path) -> Result<()> {
+    let mut content = String::from("# import cycles known to snob, see `snob cycles --baseline`\n");
+    for cycle in cycles {
+        content.push_str(&relative_files(cycle, git_root).join("\t"));
+        content.push('\n');
+    }
+    std::fs::write(path, content)
+        .with_context(|| format!("Failed to write the cycles baseline {}", path.display()))
+}
+
+/// The indices of the cycles that are not part of any cycle of the baseline: new cycles, as well
+/// as known ones that grew to include other files
+pub fn new_cycles(
+    cycles: &[ImportCycle],
+    baseline: &[BTreeSet<String>],
+    git_root: &Path,
+) -> FxHashSet<usize> {
+    cycles
+        .iter()
+        .enumerate()
+        .filter(|(_, cycle)| {
+            let files = relative_files(cycle, git_root);
+            !baseline
+                .iter()
+                .any(|known| files.iter().all(|f| known.contains(f)))
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+fn relative_files(cycle: &ImportCycle, git_root: &Path) -> Vec<String> {
+    cycle
+        .files
+        .iter()
+        .map(|f| utils::relative_to(f, git_root))
+        .collect()
+}
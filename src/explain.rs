@@ -0,0 +1,141 @@
+use anyhow::Result;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::path::Path;
+
+use crate::analysis::Analysis;
+use crate::ast::{ImportSite, UnresolvedImport};
+use crate::config::Config;
+use crate::pytest::TestFileMatcher;
+use crate::utils::relative_to;
+use crate::{fs, graph};
+
+/// A file of an import chain, along with the statement importing the previous file of the chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportStep {
+    pub file: String,
+    /// `None` for the first file of the chain
+    pub site: Option<ImportSite>,
+}
+
+/// Explain why `test` is impacted by the changed files: the shortest chain of imports leading
+/// from each changed file it depends on to the test
+pub fn why(analysis: &Analysis, test: &str, changed: &FxHashSet<String>) -> Vec<Vec<ImportStep>> {
+    why_with(
+        analysis,
+        &graph::reverse_graph(&analysis.dependency_graph),
+        test,
+        changed,
+    )
+}
+
+/// Same as `why`, reusing the reversed dependency graph (see `graph::reverse_graph`) when
+/// explaining many tests
+pub fn why_with<'a>(
+    analysis: &Analysis,
+    dependencies_of: &FxHashMap<&'a str, Vec<&'a str>>,
+    test: &'a str,
+    changed: &FxHashSet<String>,
+) -> Vec<Vec<ImportStep>> {
+    graph::shortest_import_chains(dependencies_of, test, changed)
+        .into_iter()
+        .map(|chain| {
+            let mut previous: Option<&String> = None;
+            chain
+                .iter()
+                .map(|file| {
+                    let site = previous.and_then(|imported| {
+                        analysis
+                            .import_edges
+                            .get(imported)?
+                            .iter()
+                            .filter(|edge| edge.importer == *file)
+                            .map(|edge| edge.site)
+                            .min_by_key(|site| site.line)
+                    });
+                    previous = Some(file);
+                    ImportStep {
+                        file: file.clone(),
+                        site,
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// What may keep a test from being selected
+#[derive(Debug, Default)]
+pub struct WhyNot {
+    /// the changed files the test depends on (none when it is not impacted)
+    pub impacted_by: Vec<String>,
+    /// the test was not found among the analyzed files
+    pub not_analyzed: bool,
+    /// the test doesn't match the test discovery settings (`python_files`, `testpaths`, ...)
+    pub not_a_test: bool,
+    /// the test matches `tests.ignores` (and not `tests.always-run`)
+    pub ignored_test: bool,
+    /// the test and those of its dependencies that match `files.ignores`: their imports are not
+    /// followed
+    pub ignored_files: Vec<String>,
+    /// (file, import) the imports of the test and of its dependencies that could not be resolved
+    pub unresolved_imports: Vec<(String, UnresolvedImport)>,
+    /// the test and those of its dependencies that could not be parsed
+    pub parse_failures: Vec<String>,
+}
+
+/// Explain why `test` may not be selected for the changed files, looking at the test and at the
+/// files it depends on for the usual suspects
+pub fn why_not(
+    analysis: &Analysis,
+    config: &Config,
+    test_matcher: &TestFileMatcher,
+    git_root: &Path,
+    test: &str,
+    changed: &FxHashSet<String>,
+) -> Result<WhyNot> {
+    let file_ignores = fs::build_glob_set(&config.files.ignores)?;
+    let test_ignores = fs::build_glob_set(&config.tests.ignores)?;
+    let always_run = fs::build_glob_set(&config.tests.always_run)?;
+
+    let mut dependencies = graph::discover_dependencies(
+        &analysis.dependency_graph,
+        &FxHashSet::from_iter([test.to_string()]),
+    )
+    .into_iter()
+    .collect::<Vec<_>>();
+    dependencies.sort();
+
+    let test = test.to_string();
+    Ok(WhyNot {
+        impacted_by: dependencies
+            .iter()
+            .filter(|f| changed.contains(*f))
+            .cloned()
+            .collect(),
+        not_analyzed: !analysis.project_files.contains(&test),
+        not_a_test: !test_matcher.is_test_file(&test),
+        ignored_test: test_ignores.is_match(relative_to(&test, git_root))
+            && !always_run.is_match(relative_to(&test, git_root)),
+        ignored_files: dependencies
+            .iter()
+            .filter(|f| file_ignores.is_match(relative_to(f, git_root)))
+            .cloned()
+            .collect(),
+        unresolved_imports: dependencies
+            .iter()
+            .flat_map(|f| {
+                analysis
+                    .unresolved_imports
+                    .get(f)
+                    .into_iter()
+                    .flatten()
+                    .map(|import| (f.clone(), import.clone()))
+            })
+            .collect(),
+        parse_failures: dependencies
+            .iter()
+            .filter(|f| analysis.parse_failures.contains(*f))
+            .cloned()
+            .collect(),
+    })
+}
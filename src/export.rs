@@ -0,0 +1,377 @@
+use anyhow::Result;
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::path::Path;
+
+use crate::analysis::Analysis;
+use crate::pytest::TestFileMatcher;
+use crate::utils::{relative_to, LookupPaths};
+
+/// The formats the dependency graph can be exported to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// adjacency lists, along with the attributes of each node
+    Json,
+    /// for Gephi, yEd and friends
+    GraphMl,
+    /// for markdown documents (e.g. PR descriptions)
+    Mermaid,
+}
+
+impl GraphFormat {
+    /// The format matching the extension of `path` (`.json`, `.graphml`, `.mmd` or `.mermaid`)
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "json" => Some(Self::Json),
+            "graphml" => Some(Self::GraphMl),
+            "mmd" | "mermaid" => Some(Self::Mermaid),
+            _ => None,
+        }
+    }
+}
+
+/// A file of the exported graph
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct GraphNode {
+    /// dotted module name (e.g. `app.orders`)
+    pub module: String,
+    /// the package the module belongs to (itself for `__init__.py` files)
+    pub package: String,
+    pub is_test: bool,
+    pub is_changed: bool,
+    /// whether the file could not be parsed, so that its imports are unknown
+    pub parse_failed: bool,
+    /// the files it imports, relative to the project root
+    pub imports: Vec<String>,
+}
+
+/// The dependency graph (or part of it) along with the attributes of its files, keyed by path
+/// relative to the project root
+#[derive(Debug, Default, Serialize)]
+pub struct ExportedGraph {
+    pub nodes: BTreeMap<String, GraphNode>,
+}
+
+impl ExportedGraph {
+    /// Export the subgraph of `analysis` made of `files` (absolute paths) and the imports
+    /// between them
+    pub fn new<'a>(
+        analysis: &Analysis,
+        files: impl IntoIterator<Item = &'a String>,
+        changed: &FxHashSet<String>,
+        test_matcher: &TestFileMatcher,
+        lookup_paths: &LookupPaths,
+        git_root: &Path,
+    ) -> Self {
+        let files = files.into_iter().collect::<FxHashSet<_>>();
+
+        // the dependency graph goes from imported files to the files importing them
+        let mut imports: FxHashMap<&String, Vec<String>> = FxHashMap::default();
+        for (imported, consumers) in &analysis.dependency_graph {
+            if !files.contains(imported) {
+                continue;
+            }
+            for consumer in consumers.iter().filter(|c| files.contains(c)) {
+                imports
+                    .entry(consumer)
+                    .or_default()
+                    .push(relative_to(imported, git_root));
+            }
+        }
+
+        let nodes = files
+            .iter()
+            .map(|file| {
+                let module = module_name(file, lookup_paths, git_root);
+                let mut imports = imports.remove(file).unwrap_or_default();
+                imports.sort();
+                let node = GraphNode {
+                    package: package_name(file, &module),
+                    module,
+                    is_test: test_matcher.is_test_file(file),
+                    is_changed: changed.contains(*file),
+                    parse_failed: analysis.parse_failures.contains(*file),
+                    imports,
+                };
+                (relative_to(file, git_root), node)
+            })
+            .collect();
+        Self { nodes }
+    }
+
+    pub fn write(&self, format: GraphFormat, writer: &mut impl Write) -> Result<()> {
+        match format {
+            GraphFormat::Json => {
+                serde_json::to_writer_pretty(&mut *writer, self)?;
+                writeln!(writer)?;
+            }
+            GraphFormat::GraphMl => self.write_graphml(writer)?,
+            GraphFormat::Mermaid => self.write_mermaid(writer)?,
+        }
+        Ok(())
+    }
+
+    fn write_graphml(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            writer,
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+        )?;
+        for (key, kind) in GRAPHML_KEYS {
+            writeln!(
+                writer,
+                r#"  <key id="{key}" for="node" attr.name="{key}" attr.type="{kind}"/>"#
+            )?;
+        }
+        writeln!(writer, r#"  <graph id="snob" edgedefault="directed">"#)?;
+        for (path, node) in &self.nodes {
+            writeln!(writer, r#"    <node id="{}">"#, escape_xml(path))?;
+            let values = [
+                escape_xml(&node.module),
+                escape_xml(&node.package),
+                node.is_test.to_string(),
+                node.is_changed.to_string(),
+                node.parse_failed.to_string(),
+            ];
+            for ((key, _), value) in GRAPHML_KEYS.iter().zip(values) {
+                writeln!(writer, r#"      <data key="{key}">{value}</data>"#)?;
+            }
+            writeln!(writer, "    </node>")?;
+        }
+        for (path, node) in &self.nodes {
+            for imported in &node.imports {
+                writeln!(
+                    writer,
+                    r#"    <edge source="{}" target="{}"/>"#,
+                    escape_xml(path),
+                    escape_xml(imported)
+                )?;
+            }
+        }
+        writeln!(writer, "  </graph>")?;
+        writeln!(writer, "</graphml>")
+    }
+
+    fn write_mermaid(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        // mermaid ids can't contain most punctuation, nodes are numbered in path order instead
+        let ids = self
+            .nodes
+            .keys()
+            .enumerate()
+            .map(|(i, path)| (path, format!("n{i}")))
+            .collect::<FxHashMap<_, _>>();
+
+        writeln!(writer, "graph LR")?;
+        for (path, node) in &self.nodes {
+            let class = if node.parse_failed {
+                ":::parseFailed"
+            } else if node.is_changed {
+                ":::changed"
+            } else if node.is_test {
+                ":::test"
+            } else {
+                ""
+            };
+            writeln!(
+                writer,
+                "    {}[\"{}\"]{class}",
+                ids[path],
+                path.replace('"', "#quot;")
+            )?;
+        }
+        for (path, node) in &self.nodes {
+            for imported in node.imports.iter().filter_map(|i| ids.get(i)) {
+                writeln!(writer, "    {} --> {imported}", ids[path])?;
+            }
+        }
+        writeln!(writer, "    classDef changed fill:#f96")?;
+        writeln!(writer, "    classDef test fill:#9cf")?;
+        writeln!(
+            writer,
+            "    classDef parseFailed stroke:#f00,stroke-dasharray:4"
+        )
+    }
+
+    /// Write the graph in DOT (see https://graphviz.org/doc/info/lang.html), labelling files with
+    /// their module name and grouping them in a cluster per package.
+    ///
+    /// Changed files, the other non-test modules and the `selected` tests (paths relative to the
+    /// project root) are filled with different colors. Packages of more than `collapse_threshold`
+    /// files are drawn as a single node.
+    pub fn write_dot(
+        &self,
+        selected: &FxHashSet<String>,
+        collapse_threshold: Option<usize>,
+        writer: &mut impl Write,
+    ) -> std::io::Result<()> {
+        let mut packages: BTreeMap<&str, Vec<(&String, &GraphNode)>> = BTreeMap::new();
+        for (path, node) in &self.nodes {
+            packages
+                .entry(&node.package)
+                .or_default()
+                .push((path, node));
+        }
+
+        writeln!(writer, "digraph G {{")?;
+        writeln!(writer, "    rankdir=LR;")?;
+        writeln!(
+            writer,
+            "    node [shape=box, style=filled, fillcolor=white];"
+        )?;
+
+        // the DOT node each file is drawn as
+        let mut dot_nodes: FxHashMap<&String, String> = FxHashMap::default();
+        for (i, (package, files)) in packages.iter().enumerate() {
+            let status = |(path, node): &(&String, &GraphNode)| {
+                DotStatus::new(node, selected.contains(*path))
+            };
+            let collapse =
+                !package.is_empty() && collapse_threshold.is_some_and(|t| files.len() > t);
+            if collapse {
+                let id = format!("package:{package}");
+                let fill = files.iter().map(status).min().unwrap_or(DotStatus::Other);
+                writeln!(
+                    writer,
+                    "    {} [label={}, shape=folder, fillcolor=\"{}\"];",
+                    quote_dot(&id),
+                    quote_dot(&format!("{package} ({} files)", files.len())),
+                    fill.color()
+                )?;
+                dot_nodes.extend(files.iter().map(|(path, _)| (*path, id.clone())));
+                continue;
+            }
+
+            // modules outside of any package are not clustered
+            let indent = if package.is_empty() {
+                "    "
+            } else {
+                "        "
+            };
+            if !package.is_empty() {
+                writeln!(writer, "    subgraph cluster_{i} {{")?;
+                writeln!(writer, "        label={};", quote_dot(package))?;
+            }
+            for file in files {
+                let (path, node) = file;
+                writeln!(
+                    writer,
+                    "{indent}{} [label={}, fillcolor=\"{}\"];",
+                    quote_dot(path),
+                    quote_dot(&node.module),
+                    status(file).color()
+                )?;
+                dot_nodes.insert(path, path.to_string());
+            }
+            if !package.is_empty() {
+                writeln!(writer, "    }}")?;
+            }
+        }
+
+        let edges = self
+            .nodes
+            .iter()
+            .flat_map(|(path, node)| {
+                node.imports
+                    .iter()
+                    .filter_map(|imported| Some((&dot_nodes[path], dot_nodes.get(imported)?)))
+            })
+            // imports within a collapsed package
+            .filter(|(importer, imported)| importer != imported)
+            .collect::<BTreeSet<_>>();
+        for (importer, imported) in edges {
+            writeln!(
+                writer,
+                "    {} -> {};",
+                quote_dot(importer),
+                quote_dot(imported)
+            )?;
+        }
+        writeln!(writer, "}}")
+    }
+}
+
+/// How a file of the DOT graph is highlighted, by decreasing priority
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum DotStatus {
+    Changed,
+    SelectedTest,
+    Module,
+    Other,
+}
+
+impl DotStatus {
+    fn new(node: &GraphNode, selected: bool) -> Self {
+        match (node.is_changed, node.is_test) {
+            (true, _) => Self::Changed,
+            (false, true) if selected => Self::SelectedTest,
+            (false, true) => Self::Other,
+            (false, false) => Self::Module,
+        }
+    }
+
+    fn color(self) -> &'static str {
+        match self {
+            Self::Changed => "#ff9966",
+            Self::SelectedTest => "#99ccff",
+            Self::Module => "#ffe699",
+            Self::Other => "white",
+        }
+    }
+}
+
+fn quote_dot(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// (attribute, GraphML type), in the order of the values written for each node
+const GRAPHML_KEYS: [(&str, &str); 5] = [
+    ("module", "string"),
+    ("package", "string"),
+    ("is_test", "boolean"),
+    ("is_changed", "boolean"),
+    ("parse_failed", "boolean"),
+];
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// The dotted name `file` is imported as, relative to the closest lookup path containing it
+/// (or to the project root)
+pub fn module_name(file: &str, lookup_paths: &LookupPaths, git_root: &Path) -> String {
+    let file = Path::new(file);
+    let relative = lookup_paths
+        .local_paths
+        .iter()
+        .filter_map(|lookup_path| file.strip_prefix(lookup_path).ok())
+        .min_by_key(|relative| relative.components().count())
+        .or_else(|| file.strip_prefix(git_root).ok())
+        .unwrap_or(file);
+    let mut parts = relative
+        .with_extension("")
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<_>>();
+    if parts.last().is_some_and(|p| p == "__init__") {
+        parts.pop();
+    }
+    parts.join(".")
+}
+
+/// The package a module belongs to: the module itself for packages (`__init__.py`), its parent
+/// otherwise
+pub fn package_name(file: &str, module: &str) -> String {
+    if Path::new(file).file_stem().is_some_and(|s| s == "__init__") {
+        return module.to_string();
+    }
+    module
+        .rsplit_once('.')
+        .map(|(package, _)| package.to_string())
+        .unwrap_or_default()
+}
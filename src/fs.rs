@@ -1,13 +1,17 @@
 use std::path::{Path, PathBuf};
 
 use crate::ast::INIT_FILE;
-use crate::utils::LookupPaths;
+use crate::source::SourceProvider;
+use crate::utils::{normalize_path, LookupPaths};
 use globset::{Glob, GlobSet, GlobSetBuilder};
 use ignore::{types::TypesBuilder, DirEntry, WalkBuilder};
 use rustc_hash::FxHashSet;
 
-fn create_walk_builder(current_dir: &std::path::PathBuf) -> WalkBuilder {
-    let mut builder = WalkBuilder::new(current_dir);
+fn create_walk_builder(roots: &[PathBuf]) -> WalkBuilder {
+    let mut builder = WalkBuilder::new(&roots[0]);
+    for root in &roots[1..] {
+        builder.add(root);
+    }
 
     // only python files
     let mut types_builder = TypesBuilder::new();
@@ -18,13 +22,46 @@ fn create_walk_builder(current_dir: &std::path::PathBuf) -> WalkBuilder {
     builder
 }
 
-/// Crawl the workspace and return a list of files and directories
+/// Compute the set of directories to crawl
 /// # Arguments
-/// * `current_dir` - The directory to start the crawl from
+/// * `target_directory` - The directory snob was asked to analyze
+/// * `lookup_paths` - The in-repo lookup paths used by python's import system
+/// * `workspace_roots` - The root directories of the workspace members, if any
 /// # Returns
-/// * A tuple containing a list of files and a list of directories
-pub fn crawl_workspace(current_dir: &std::path::PathBuf) -> Vec<std::path::PathBuf> {
-    let builder = create_walk_builder(current_dir);
+/// * The target directory, lookup roots and workspace roots, without the ones nested inside
+///   another root
+pub fn get_crawl_roots(
+    target_directory: &Path,
+    lookup_paths: &LookupPaths,
+    workspace_roots: &[PathBuf],
+) -> Vec<PathBuf> {
+    let mut candidates = std::iter::once(target_directory)
+        .chain(lookup_paths.local_paths.iter().map(PathBuf::as_path))
+        .chain(workspace_roots.iter().map(PathBuf::as_path))
+        .map(Path::to_path_buf)
+        .collect::<Vec<_>>();
+    // shallower directories first so that nested roots are found to be redundant
+    candidates.sort_by_key(|p| p.components().count());
+
+    let mut roots: Vec<PathBuf> = Vec::new();
+    for candidate in candidates {
+        if !roots.iter().any(|root| candidate.starts_with(root)) {
+            roots.push(candidate);
+        }
+    }
+    roots
+}
+
+/// Crawl the workspace and return a list of files
+/// # Arguments
+/// * `roots` - The directories to start the crawl from (see `get_crawl_roots`)
+/// # Returns
+/// * A list of python files found under any of the roots
+pub fn crawl_workspace(roots: &[PathBuf]) -> Vec<std::path::PathBuf> {
+    if roots.is_empty() {
+        return Vec::new();
+    }
+    let builder = create_walk_builder(roots);
     let (tx_file_handle, rx_file_handle) = std::sync::mpsc::channel();
 
     let parallel_walker = builder.build_parallel();
@@ -52,19 +89,39 @@ pub fn crawl_workspace(current_dir: &std::path::PathBuf) -> Vec<std::path::PathB
     rx_file_handle.try_iter().collect()
 }
 
-pub fn check_files_exist<P>(files: &FxHashSet<P>) -> Result<(), std::io::Error>
-where
-    P: AsRef<Path>,
-{
-    for file in files {
-        if !file.as_ref().exists() {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                format!("File {:?} does not exist", file.as_ref()),
-            ));
-        }
-    }
-    Ok(())
+/// Changed files that don't exist (anymore) in the analyzed sources, which are considered deleted
+pub fn find_deleted_files(
+    files: &FxHashSet<String>,
+    source: &dyn SourceProvider,
+) -> FxHashSet<String> {
+    files
+        .iter()
+        .filter(|f| !source.exists(Path::new(f)))
+        .cloned()
+        .collect()
+}
+
+/// The modules and packages defined at the top level of the lookup paths (see
+/// `get_first_level_components`) that deleted files belonged to: their former consumers still
+/// import them
+pub fn get_deleted_first_level_components(
+    deleted_files: &FxHashSet<String>,
+    lookup_paths: &LookupPaths,
+) -> Vec<PathBuf> {
+    let mut components = deleted_files
+        .iter()
+        .map(Path::new)
+        .filter(|file| file.extension().is_some_and(|ext| ext == "py"))
+        .flat_map(|file| {
+            lookup_paths.local_paths.iter().filter_map(|lookup_path| {
+                let top_level = file.strip_prefix(lookup_path).ok()?.components().next()?;
+                Some(lookup_path.join(top_level))
+            })
+        })
+        .collect::<Vec<_>>();
+    components.sort();
+    components.dedup();
+    components
 }
 
 pub fn make_files_relative_to<P>(files: &[P], base: &Path) -> Vec<String>
@@ -74,12 +131,8 @@ where
     files
         .iter()
         .map(|f| {
-            let p = PathBuf::from(f.as_ref());
-            if p.is_relative() {
-                base.join(p).to_string_lossy().to_string()
-            } else {
-                p.to_string_lossy().to_string()
-            }
+            // `..` components would keep the files from matching the crawled paths
+            normalize_path(&base.join(f)).to_string_lossy().to_string()
         })
         .collect::<Vec<_>>()
 }
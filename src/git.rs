@@ -0,0 +1,214 @@
+use anyhow::Result;
+use rustc_hash::FxHashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::{snob_debug, snob_warn};
+
+/// The changes of the repository snob should select tests for
+#[derive(Debug, Default, Clone)]
+pub struct ChangeSelection {
+    /// files changed by the commits between the merge base of this revision and `revision`
+    pub base: Option<String>,
+    /// changes added to the index
+    pub staged: bool,
+    /// changes of the working tree that were not added to the index
+    pub unstaged: bool,
+    /// new files that are not ignored
+    pub untracked: bool,
+    /// the revision `base` is compared to (`HEAD` by default)
+    pub revision: Option<String>,
+}
+
+impl ChangeSelection {
+    pub fn is_empty(&self) -> bool {
+        self.base.is_none() && !self.staged && !self.unstaged && !self.untracked
+    }
+
+    pub fn revision(&self) -> &str {
+        self.revision.as_deref().unwrap_or("HEAD")
+    }
+}
+
+/// The best common ancestor of `base` and `revision`
+pub fn merge_base(repo_root: &Path, base: &str, revision: &str) -> Result<String> {
+    let merge_base = run_git(repo_root, &["merge-base", base, revision])?
+        .trim()
+        .to_string();
+    snob_debug!("Merge base of {} and {}: {}", base, revision, merge_base);
+    Ok(merge_base)
+}
+
+/// `git hash-object -t tree /dev/null`, to diff against when there is no previous revision
+pub const EMPTY_TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+/// Compute the files changed in the repository at `repo_root`, as absolute paths.
+///
+/// Deleted files are included (their former consumers are impacted) and renames are
+/// treated as a deletion of the old path along with the addition of the new one.
+/// The changes of checked out submodules are included as well.
+pub fn get_changed_files(repo_root: &Path, selection: &ChangeSelection) -> Result<Vec<String>> {
+    let mut changed_files = changed_paths(repo_root, selection)?
+        .into_iter()
+        .map(|f| f.to_string_lossy().to_string())
+        .collect::<Vec<_>>();
+    changed_files.sort();
+    snob_debug!("Files changed according to git: {:?}", changed_files);
+    Ok(changed_files)
+}
+
+fn changed_paths(repo_root: &Path, selection: &ChangeSelection) -> Result<FxHashSet<PathBuf>> {
+    let mut changed_files = FxHashSet::default();
+
+    let merge_base = match &selection.base {
+        Some(base) => Some(merge_base(repo_root, base, selection.revision())?),
+        None => None,
+    };
+    if let Some(merge_base) = &merge_base {
+        changed_files.extend(git_diff(repo_root, &[merge_base, selection.revision()])?);
+    }
+    if selection.staged {
+        changed_files.extend(git_diff(repo_root, &["--cached"])?);
+    }
+    if selection.unstaged {
+        changed_files.extend(git_diff(repo_root, &[])?);
+    }
+    if selection.untracked {
+        changed_files.extend(split_paths(&run_git(
+            repo_root,
+            &["ls-files", "-z", "--others", "--exclude-standard"],
+        )?));
+    }
+    let mut paths = changed_files
+        .into_iter()
+        .map(|f| repo_root.join(f))
+        .collect::<FxHashSet<_>>();
+
+    // a submodule only shows up as a single (gitlink) entry in its superproject's changes
+    for submodule in checked_out_submodules(repo_root)? {
+        let submodule_root = repo_root.join(&submodule);
+        paths.remove(&submodule_root);
+
+        if let Some(merge_base) = &merge_base {
+            let old = gitlink(repo_root, merge_base, &submodule)?;
+            let new = gitlink(repo_root, selection.revision(), &submodule)?;
+            if old != new {
+                let old = old.as_deref().unwrap_or(EMPTY_TREE);
+                let new = new.as_deref().unwrap_or(EMPTY_TREE);
+                match git_diff(&submodule_root, &[old, new]) {
+                    Ok(files) => paths.extend(files.iter().map(|f| submodule_root.join(f))),
+                    Err(e) => snob_warn!("Skipping the changes of submodule {}: {}", submodule, e),
+                }
+            }
+        }
+        let working_tree_selection = ChangeSelection {
+            base: None,
+            revision: None,
+            ..selection.clone()
+        };
+        if !working_tree_selection.is_empty() {
+            paths.extend(changed_paths(&submodule_root, &working_tree_selection)?);
+        }
+    }
+
+    Ok(paths)
+}
+
+// paths of the submodules of the index that are checked out
+fn checked_out_submodules(repo_root: &Path) -> Result<Vec<String>> {
+    // <mode> SP <object> SP <stage> TAB <path>
+    let output = run_git(repo_root, &["ls-files", "-z", "--stage"])?;
+    Ok(split_paths(&output)
+        .into_iter()
+        .filter_map(|entry| {
+            let (info, path) = entry.split_once('\t')?;
+            info.starts_with(GITLINK_MODE).then(|| path.to_string())
+        })
+        .filter(|path| repo_root.join(path).join(".git").exists())
+        .collect())
+}
+
+const GITLINK_MODE: &str = "160000";
+
+// the commit a submodule is at in `revision` of its superproject
+fn gitlink(repo_root: &Path, revision: &str, submodule: &str) -> Result<Option<String>> {
+    // <mode> SP <type> SP <object> TAB <path>
+    let output = run_git(repo_root, &["ls-tree", "-z", revision, "--", submodule])?;
+    Ok(output
+        .split('\0')
+        .filter_map(|entry| entry.split_once('\t'))
+        .find(|(info, _)| info.starts_with(GITLINK_MODE))
+        .and_then(|(info, _)| info.split(' ').nth(2))
+        .map(ToString::to_string))
+}
+
+// `repo_root` may be below the git toplevel (e.g. a package of a monorepo): `--relative` makes
+// the paths relative to it, like the ones of `git ls-files`, and leaves out the changes outside
+fn git_diff(repo_root: &Path, args: &[&str]) -> Result<Vec<String>> {
+    let mut diff_args = vec!["diff", "-z", "--name-status", "-M", "--relative"];
+    diff_args.extend(args);
+    Ok(parse_name_status(&run_git(repo_root, &diff_args)?))
+}
+
+/// A path mentioned in a list of changes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    pub path: String,
+    /// whether the path no longer exists after the change (deletions and renames' old paths)
+    pub deleted: bool,
+}
+
+/// Parse the output of `git diff --name-status -z`, returning every path it mentions.
+///
+/// Each entry is a status followed by a path, or by two paths for renames and copies
+/// (e.g. `R086\0old.py\0new.py\0`).
+pub fn parse_name_status(output: &str) -> Vec<String> {
+    parse_name_status_fields(output.split('\0'))
+        .into_iter()
+        .map(|change| change.path)
+        .collect()
+}
+
+/// Parse the fields of `git diff --name-status` (statuses and paths), however they were split
+pub fn parse_name_status_fields<'a, I>(fields: I) -> Vec<FileChange>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut fields = fields.into_iter().filter(|f| !f.is_empty());
+    let mut changes = Vec::new();
+    while let Some(status) = fields.next() {
+        let path_count = if status.starts_with(['R', 'C']) { 2 } else { 1 };
+        for (i, path) in fields.by_ref().take(path_count).enumerate() {
+            changes.push(FileChange {
+                path: path.to_string(),
+                deleted: status.starts_with('D') || (status.starts_with('R') && i == 0),
+            });
+        }
+    }
+    changes
+}
+
+// paths are NUL separated (`-z`) so that git does not quote unusual file names
+fn split_paths(output: &str) -> Vec<String> {
+    output
+        .split('\0')
+        .filter(|p| !p.is_empty())
+        .map(std::string::ToString::to_string)
+        .collect()
+}
+
+pub fn run_git(repo_root: &Path, args: &[&str]) -> Result<String> {
+    snob_debug!("Running git {:?}", args);
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(repo_root)
+        .output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "`git {}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
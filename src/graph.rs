@@ -1,84 +1,362 @@
-use crate::ast::extract_file_dependencies;
+use crate::ast::{extract_file_dependencies, ImportEdge, TestDetection, UnresolvedImport};
 use crate::snob_error;
+use crate::source::SourceProvider;
+use crate::utils::{relative_to, LookupPaths};
 use globset::GlobSet;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use rustc_hash::{FxHashMap, FxHashSet};
-use std::path::Path;
-use std::{
-    io::{BufWriter, Write},
-    path::PathBuf,
-};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
 
 pub fn discover_impacted_nodes(
     dependency_graph: &FxHashMap<String, FxHashSet<String>>,
     updated_files: &FxHashSet<String>,
 ) -> FxHashSet<String> {
-    let mut impacted_nodes = FxHashSet::default();
-    let mut stack = updated_files.iter().cloned().collect::<Vec<_>>();
-    while let Some(file) = stack.pop() {
-        if impacted_nodes.contains(&file) {
-            continue;
-        }
+    discover_impacted_nodes_with_predecessors(dependency_graph, updated_files)
+        .predecessors
+        .into_keys()
+        .collect()
+}
 
-        impacted_nodes.insert(file.clone());
-        if let Some(consumers) = dependency_graph.get(&file) {
-            stack.extend(consumers.iter().cloned());
+/// The files impacted by a change, each with the file it was reached from
+#[derive(Debug, Default)]
+pub struct ImpactedNodes {
+    /// impacted file -> the file it imports that was impacted first (`None` for changed files)
+    predecessors: FxHashMap<String, Option<String>>,
+}
+
+impl ImpactedNodes {
+    pub fn nodes(&self) -> impl Iterator<Item = &String> {
+        self.predecessors.keys()
+    }
+
+    /// The files leading from a changed file to `file` (both included), `None` when `file` is
+    /// not impacted
+    pub fn chain(&self, file: &str) -> Option<Vec<String>> {
+        let mut chain = vec![file.to_string()];
+        let mut current = self.predecessors.get(file)?;
+        while let Some(predecessor) = current {
+            chain.push(predecessor.clone());
+            current = &self.predecessors[predecessor];
         }
+        chain.reverse();
+        Some(chain)
     }
-    impacted_nodes
 }
 
-pub fn discover_impacted_nodes_with_graphviz(
+/// Same as `discover_impacted_nodes`, keeping track of how each file was reached.
+///
+/// The graph is walked breadth first, in a stable order, so that chains are as short as possible
+/// and don't change from one run to the next.
+pub fn discover_impacted_nodes_with_predecessors(
     dependency_graph: &FxHashMap<String, FxHashSet<String>>,
     updated_files: &FxHashSet<String>,
-    dot_graph: &PathBuf,
+) -> ImpactedNodes {
+    let mut predecessors = FxHashMap::default();
+    let mut queue = VecDeque::new();
+
+    let mut updated_files = updated_files.iter().collect::<Vec<_>>();
+    updated_files.sort();
+    for file in updated_files {
+        predecessors.insert(file.clone(), None);
+        queue.push_back(file);
+    }
+
+    while let Some(file) = queue.pop_front() {
+        if let Some(consumers) = dependency_graph.get(file) {
+            let mut consumers = consumers.iter().collect::<Vec<_>>();
+            consumers.sort();
+            for consumer in consumers {
+                if !predecessors.contains_key(consumer) {
+                    predecessors.insert(consumer.clone(), Some(file.clone()));
+                    queue.push_back(consumer);
+                }
+            }
+        }
+    }
+    ImpactedNodes { predecessors }
+}
+
+/// The opposite walk of `discover_impacted_nodes`: the files `files` depend on, directly or
+/// not (including themselves)
+pub fn discover_dependencies(
+    dependency_graph: &FxHashMap<String, FxHashSet<String>>,
+    files: &FxHashSet<String>,
 ) -> FxHashSet<String> {
-    let file_handle = std::fs::File::create(dot_graph).unwrap();
-    let mut writer = BufWriter::new(file_handle);
-    writeln!(writer, "digraph G {{").unwrap();
+    let dependencies_of = reverse_graph(dependency_graph);
 
-    let mut impacted_nodes = FxHashSet::default();
-    let mut stack = updated_files.iter().cloned().collect::<Vec<_>>();
+    let mut dependencies = FxHashSet::default();
+    let mut stack = files.iter().map(String::as_str).collect::<Vec<_>>();
     while let Some(file) = stack.pop() {
-        if impacted_nodes.contains(&file) {
+        if !dependencies.insert(file.to_string()) {
             continue;
         }
+        if let Some(imported) = dependencies_of.get(file) {
+            stack.extend(imported.iter().copied());
+        }
+    }
+    dependencies
+}
 
-        impacted_nodes.insert(file.clone());
-        if let Some(consumers) = dependency_graph.get(&file) {
-            stack.extend(consumers.iter().cloned());
-            for consumer in consumers {
-                writeln!(writer, "    \"{consumer}\" -> \"{file}\";").unwrap();
+/// The shortest chain of imports from each of `sources` that `target` depends on, to `target`
+/// (both included), in a stable order.
+/// Walks the reversed dependency graph (see `reverse_graph`), which can be shared by many targets
+pub fn shortest_import_chains<'a>(
+    dependencies_of: &FxHashMap<&'a str, Vec<&'a str>>,
+    target: &'a str,
+    sources: &FxHashSet<String>,
+) -> Vec<Vec<String>> {
+    // walk up from the target, breadth first: file -> the file it was reached from
+    let mut successors: FxHashMap<&str, Option<&str>> = FxHashMap::default();
+    successors.insert(target, None);
+    let mut queue = VecDeque::from([target]);
+    let mut chains = Vec::new();
+    while let Some(file) = queue.pop_front() {
+        if sources.contains(file) {
+            let mut chain = vec![file.to_string()];
+            let mut current = successors[file];
+            while let Some(successor) = current {
+                chain.push(successor.to_string());
+                current = successors[successor];
+            }
+            chains.push(chain);
+        }
+        for imported in dependencies_of.get(file).into_iter().flatten() {
+            if !successors.contains_key(imported) {
+                successors.insert(imported, Some(file));
+                queue.push_back(imported);
+            }
+        }
+    }
+    chains
+}
+
+/// The files `file` imports, directly or not, each with its distance to `file` (1 for direct
+/// imports), up to `max_depth` when provided. Sorted by distance, then by path
+pub fn dependencies_of(
+    dependency_graph: &FxHashMap<String, FxHashSet<String>>,
+    file: &str,
+    max_depth: Option<usize>,
+) -> Vec<(String, usize)> {
+    let dependencies_of = reverse_graph(dependency_graph);
+    bounded_walk(file, max_depth, |f| {
+        dependencies_of.get(f).cloned().unwrap_or_default()
+    })
+}
+
+/// The files importing `file`, directly or not (see `dependencies_of`)
+pub fn dependents_of(
+    dependency_graph: &FxHashMap<String, FxHashSet<String>>,
+    file: &str,
+    max_depth: Option<usize>,
+) -> Vec<(String, usize)> {
+    bounded_walk(file, max_depth, |f| {
+        dependency_graph
+            .get(f)
+            .map(|consumers| consumers.iter().map(String::as_str).collect())
+            .unwrap_or_default()
+    })
+}
+
+/// The import cycles of the graph: its strongly connected components of more than one file
+/// (or of a single file importing itself), each of them sorted, in sorted order
+pub fn import_cycles(dependency_graph: &FxHashMap<String, FxHashSet<String>>) -> Vec<Vec<String>> {
+    // Tarjan's algorithm, iterative so that long import chains don't overflow the stack
+    let graph = reverse_graph(dependency_graph);
+    let mut files = graph.keys().copied().collect::<Vec<_>>();
+    files.sort();
+
+    let mut index: FxHashMap<&str, usize> = FxHashMap::default();
+    let mut lowlink: FxHashMap<&str, usize> = FxHashMap::default();
+    let mut stack: Vec<&str> = Vec::new();
+    let mut on_stack: FxHashSet<&str> = FxHashSet::default();
+    let mut cycles = Vec::new();
+
+    for root in files {
+        if index.contains_key(root) {
+            continue;
+        }
+        // (file, position of the next import to visit)
+        let mut call_stack = vec![(root, 0)];
+        while let Some((file, next)) = call_stack.pop() {
+            if next == 0 {
+                index.insert(file, index.len());
+                lowlink.insert(file, index[file]);
+                stack.push(file);
+                on_stack.insert(file);
+            }
+            let imports = graph.get(file).map(Vec::as_slice).unwrap_or_default();
+            if let Some(&imported) = imports.get(next) {
+                call_stack.push((file, next + 1));
+                if !index.contains_key(imported) {
+                    call_stack.push((imported, 0));
+                } else if on_stack.contains(imported) {
+                    lowlink.insert(file, lowlink[file].min(index[imported]));
+                }
+                continue;
+            }
+
+            // all imports of `file` were visited
+            if let Some(&(parent, _)) = call_stack.last() {
+                lowlink.insert(parent, lowlink[parent].min(lowlink[file]));
+            }
+            if lowlink[file] == index[file] {
+                let mut component = Vec::new();
+                while let Some(member) = stack.pop() {
+                    on_stack.remove(member);
+                    component.push(member.to_string());
+                    if member == file {
+                        break;
+                    }
+                }
+                if component.len() > 1 || imports.contains(&file) {
+                    component.sort();
+                    cycles.push(component);
+                }
+            }
+        }
+    }
+
+    cycles.sort();
+    cycles
+}
+
+// breadth first walk from `start` (excluded)
+fn bounded_walk<'a, F>(start: &'a str, max_depth: Option<usize>, next: F) -> Vec<(String, usize)>
+where
+    F: Fn(&str) -> Vec<&'a str>,
+{
+    let mut depths: FxHashMap<&str, usize> = FxHashMap::from_iter([(start, 0)]);
+    let mut queue = VecDeque::from([start]);
+    while let Some(file) = queue.pop_front() {
+        let depth = depths[file];
+        if max_depth.is_some_and(|max_depth| depth >= max_depth) {
+            continue;
+        }
+        for next_file in next(file) {
+            if !depths.contains_key(next_file) {
+                depths.insert(next_file, depth + 1);
+                queue.push_back(next_file);
             }
         }
     }
 
-    writeln!(writer, "}}").unwrap();
-    writer.flush().unwrap();
-    impacted_nodes
+    let mut files = depths
+        .into_iter()
+        .filter(|(file, _)| *file != start)
+        .map(|(file, depth)| (file.to_string(), depth))
+        .collect::<Vec<_>>();
+    files.sort_by(|(a, a_depth), (b, b_depth)| a_depth.cmp(b_depth).then_with(|| a.cmp(b)));
+    files
 }
 
+/// The dependency graph the other way around: importing file -> imported files, sorted
+pub fn reverse_graph(
+    dependency_graph: &FxHashMap<String, FxHashSet<String>>,
+) -> FxHashMap<&str, Vec<&str>> {
+    let mut dependencies_of: FxHashMap<&str, Vec<&str>> = FxHashMap::default();
+    for (dependency, consumers) in dependency_graph {
+        for consumer in consumers {
+            dependencies_of
+                .entry(consumer.as_str())
+                .or_default()
+                .push(dependency.as_str());
+        }
+    }
+    dependencies_of
+        .values_mut()
+        .for_each(|imported| imported.sort());
+    dependencies_of
+}
+
+/// What parsing the workspace files taught us about them
+#[derive(Debug, Default)]
+pub struct ParsedWorkspace {
+    /// the dependencies of each file (see `ast::FileAnalysis`), to be merged
+    pub dependencies: Vec<FxHashMap<String, Vec<ImportEdge>>>,
+    /// files recognized as test targets by their AST
+    pub test_targets: FxHashSet<String>,
+    /// file -> its imports that could not be resolved to project files
+    pub unresolved_imports: FxHashMap<String, Vec<UnresolvedImport>>,
+    /// files that could not be read or parsed, whose imports are unknown
+    pub parse_failures: FxHashSet<String>,
+}
+
+/// Parse every workspace file (apart from ignored ones), returning the dependencies of each of
+/// them as well as the files recognized as test targets by their AST (`test_detection` being
+/// given the path of each file relative to `git_root`)
 pub fn build_dependency_graph(
     workspace_files: &[PathBuf],
     project_files: &FxHashSet<String>,
     file_ignores: &GlobSet,
     first_level_components: &[PathBuf],
     git_root: &Path,
-) -> Vec<FxHashMap<String, Vec<String>>> {
-    workspace_files
+    test_detection: &(dyn Fn(&Path) -> TestDetection + Sync),
+    source: &dyn SourceProvider,
+) -> ParsedWorkspace {
+    let analyses = workspace_files
         .par_iter()
-        .filter(|f| {
-            file_ignores
-                .matches(PathBuf::from(f).strip_prefix(git_root).unwrap())
-                .is_empty()
-        })
-        .filter_map(|f| {
-            if let Ok(graph) = extract_file_dependencies(f, project_files, first_level_components) {
-                Some(graph)
-            } else {
+        .filter(|f| file_ignores.matches(relative_to(f, git_root)).is_empty())
+        .map(|f| {
+            let analysis = extract_file_dependencies(
+                f,
+                project_files,
+                first_level_components,
+                test_detection(f.strip_prefix(git_root).unwrap()),
+                source,
+            );
+            if analysis.is_err() {
                 snob_error!("Failed to parse file {:?}", f);
-                None
             }
+            (f.to_string_lossy().to_string(), analysis)
         })
-        .collect::<Vec<FxHashMap<String, Vec<String>>>>()
+        .collect::<Vec<_>>();
+
+    let mut parsed = ParsedWorkspace::default();
+    for (file, analysis) in analyses {
+        match analysis {
+            Ok(analysis) => {
+                if analysis.is_test_target {
+                    parsed.test_targets.insert(file.clone());
+                }
+                if !analysis.unresolved_imports.is_empty() {
+                    parsed
+                        .unresolved_imports
+                        .insert(file, analysis.unresolved_imports);
+                }
+                parsed.dependencies.push(analysis.dependencies);
+            }
+            Err(_) => {
+                parsed.parse_failures.insert(file);
+            }
+        }
+    }
+    parsed
+}
+
+/// Crawl the files provided by `source` and build their dependencies (see
+/// `build_dependency_graph`), test targets are left out
+pub fn build_dependency_graph_from_source(
+    source: &dyn SourceProvider,
+    crawl_roots: &[PathBuf],
+    lookup_paths: &LookupPaths,
+    file_ignores: &GlobSet,
+    git_root: &Path,
+) -> Vec<FxHashMap<String, Vec<ImportEdge>>> {
+    let files = source.list_files(crawl_roots);
+    let project_files = files
+        .iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect::<FxHashSet<String>>();
+    let first_level_components = source.first_level_components(lookup_paths);
+    build_dependency_graph(
+        &files,
+        &project_files,
+        file_ignores,
+        &first_level_components,
+        git_root,
+        &|_| TestDetection::default(),
+        source,
+    )
+    .dependencies
 }
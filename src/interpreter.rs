@@ -0,0 +1,268 @@
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::Result;
+use rustc_hash::FxHasher;
+
+use crate::snob_debug;
+use crate::utils::{get_pythonpath, LookupPaths};
+
+// prints one absolute `sys.path` entry per line (the empty entry stands for the working directory)
+const SYS_PATH_SCRIPT: &str =
+    "import os, sys; print('\\n'.join(os.path.abspath(p) for p in sys.path))";
+
+// files that may change the interpreter's `sys.path` without the interpreter itself changing
+// (e.g. editable installs declared in the project metadata)
+const PROJECT_CONFIG_FILES: [&str; 3] = ["pyproject.toml", "setup.cfg", "setup.py"];
+// virtual environments carry their configuration next to the interpreter's `bin/` directory
+const VENV_CONFIG_FILE: &str = "pyvenv.cfg";
+
+const SNOB_CACHE_DIR_ENV: &str = "SNOB_CACHE_DIR";
+const XDG_CACHE_HOME_ENV: &str = "XDG_CACHE_HOME";
+const HOME_ENV: &str = "HOME";
+
+/// Query the given python interpreter for its `sys.path` and keep the entries that live inside
+/// the repository.
+///
+/// The interpreter is run from `current_dir` so that the working directory entry matches the one
+/// python would use when running the tests. Results are cached on disk, keyed on the interpreter
+/// and on the files that are likely to affect its `sys.path`.
+pub fn get_interpreter_lookup_paths(
+    interpreter: &Path,
+    current_dir: &Path,
+    git_root: &Path,
+) -> Result<LookupPaths> {
+    cached_lookup_paths(
+        interpreter,
+        current_dir,
+        git_root,
+        get_cache_dir().as_deref(),
+    )
+}
+
+fn cached_lookup_paths(
+    interpreter: &Path,
+    current_dir: &Path,
+    git_root: &Path,
+    cache_dir: Option<&Path>,
+) -> Result<LookupPaths> {
+    let interpreter = resolve_interpreter(interpreter)?;
+    let cache_file = cache_dir.map(|dir| {
+        dir.join(format!(
+            "sys-path-{:016x}.txt",
+            compute_cache_key(&interpreter, current_dir, git_root)
+        ))
+    });
+
+    let sys_path = match cache_file
+        .as_ref()
+        .and_then(|f| std::fs::read_to_string(f).ok())
+    {
+        Some(cached) => {
+            snob_debug!("Using cached sys.path from {:?}", cache_file);
+            cached.lines().map(PathBuf::from).collect()
+        }
+        None => {
+            let sys_path = query_sys_path(&interpreter, current_dir)?;
+            if let Some(cache_file) = &cache_file {
+                write_cache(cache_file, &sys_path);
+            }
+            sys_path
+        }
+    };
+    snob_debug!("Interpreter sys.path: {:?}", sys_path);
+
+    Ok(filter_sys_path(sys_path, git_root))
+}
+
+fn query_sys_path(interpreter: &Path, current_dir: &Path) -> Result<Vec<PathBuf>> {
+    snob_debug!("Querying sys.path from {:?}", interpreter);
+    let output = Command::new(interpreter)
+        .args(["-c", SYS_PATH_SCRIPT])
+        .current_dir(current_dir)
+        .output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Failed to query sys.path from {:?}: {}",
+            interpreter,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|l| !l.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+fn write_cache(cache_file: &Path, sys_path: &[PathBuf]) {
+    let contents = sys_path
+        .iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+    // the cache is an optimization, failing to write it should not fail the run
+    if let Err(e) = cache_file
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|()| std::fs::write(cache_file, contents))
+    {
+        snob_debug!("Failed to write sys.path cache {:?}: {:?}", cache_file, e);
+    }
+}
+
+/// Keep the `sys.path` entries that are local directories of the repository.
+///
+/// Site-packages directories are dropped even when they live inside the repository (e.g. a
+/// `.venv` at the root of the project) since they only contain third-party code.
+pub fn filter_sys_path(sys_path: Vec<PathBuf>, git_root: &Path) -> LookupPaths {
+    let mut lookup_paths = LookupPaths::new();
+    sys_path
+        .into_iter()
+        .filter(|p| p.starts_with(git_root) && p.is_dir())
+        .filter(|p| {
+            !p.components()
+                .any(|c| c.as_os_str() == "site-packages" || c.as_os_str() == "dist-packages")
+        })
+        .for_each(|p| lookup_paths.insert(p));
+    lookup_paths
+}
+
+// bare interpreter names (e.g. `python3`) are looked up in `PATH` so that the cache key
+// reflects the actual executable
+fn resolve_interpreter(interpreter: &Path) -> Result<PathBuf> {
+    if interpreter.components().count() > 1 {
+        return Ok(std::path::absolute(interpreter)?);
+    }
+    std::env::var_os("PATH")
+        .iter()
+        .flat_map(std::env::split_paths)
+        .map(|dir| dir.join(interpreter))
+        .find(|candidate| candidate.is_file())
+        .ok_or_else(|| anyhow::anyhow!("Could not find python interpreter {:?}", interpreter))
+}
+
+fn compute_cache_key(interpreter: &Path, current_dir: &Path, git_root: &Path) -> u64 {
+    let mut hasher = FxHasher::default();
+    interpreter.hash(&mut hasher);
+    current_dir.hash(&mut hasher);
+    get_pythonpath().hash(&mut hasher);
+
+    // the interpreter path may be a symlink that was re-pointed (e.g. a recreated venv)
+    if let Ok(target) = std::fs::canonicalize(interpreter) {
+        target.hash(&mut hasher);
+        if let Ok(modified) = std::fs::metadata(&target).and_then(|m| m.modified()) {
+            modified.hash(&mut hasher);
+        }
+    }
+
+    let venv_config = interpreter
+        .parent()
+        .and_then(Path::parent)
+        .map(|venv| venv.join(VENV_CONFIG_FILE));
+    PROJECT_CONFIG_FILES
+        .iter()
+        .map(|f| git_root.join(f))
+        .chain(venv_config)
+        .for_each(|f| {
+            f.hash(&mut hasher);
+            std::fs::read(&f).ok().hash(&mut hasher);
+        });
+
+    hasher.finish()
+}
+
+fn get_cache_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os(SNOB_CACHE_DIR_ENV) {
+        return Some(PathBuf::from(dir));
+    }
+    std::env::var_os(XDG_CACHE_HOME_ENV)
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os(HOME_ENV).map(|home| PathBuf::from(home).join(".cache")))
+        .map(|dir| dir.join("snob"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_sys_path() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let git_root = temp_dir.path().to_path_buf();
+        let src = git_root.join("src");
+        let site_packages = git_root.join(".venv/lib/python3.12/site-packages");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::create_dir_all(&site_packages).unwrap();
+
+        let lookup_paths = filter_sys_path(
+            vec![
+                git_root.clone(),
+                src.clone(),
+                PathBuf::from("/usr/lib/python3.12"),
+                site_packages,
+                git_root.join("missing"),
+                src.clone(),
+            ],
+            &git_root,
+        );
+
+        assert_eq!(lookup_paths.local_paths, vec![git_root, src]);
+    }
+
+    // a fake interpreter printing `src` as its only `sys.path` entry, and counting its runs
+    #[cfg(unix)]
+    fn fake_interpreter(path: &Path, src: &Path, runs: &Path) {
+        use std::os::unix::fs::PermissionsExt;
+
+        std::fs::write(
+            path,
+            format!(
+                "#!/bin/sh\necho run >> '{}'\necho '{}'\n",
+                runs.display(),
+                src.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sys_path_cache() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let git_root = temp_dir.path().join("repo");
+        let src = git_root.join("src");
+        let bin = temp_dir.path().join("bin");
+        let runs = temp_dir.path().join("runs");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::create_dir_all(&bin).unwrap();
+        let cache_dir = temp_dir.path().join("cache");
+        let run_count = || {
+            std::fs::read_to_string(&runs)
+                .map(|r| r.lines().count())
+                .unwrap_or(0)
+        };
+
+        let lookup_paths = |python: &Path| {
+            cached_lookup_paths(python, &git_root, &git_root, Some(&cache_dir)).unwrap()
+        };
+
+        let python = bin.join("python");
+        fake_interpreter(&python, &src, &runs);
+        for _ in 0..2 {
+            assert_eq!(lookup_paths(&python).local_paths, vec![src.clone()]);
+        }
+        // the second query is answered by the cache
+        assert_eq!(run_count(), 1);
+
+        // another interpreter has its own entry
+        let other_python = bin.join("python3");
+        fake_interpreter(&other_python, &src, &runs);
+        lookup_paths(&other_python);
+        assert_eq!(run_count(), 2);
+        lookup_paths(&other_python);
+        assert_eq!(run_count(), 2);
+    }
+}
@@ -1,28 +1,40 @@
 #[cfg(feature = "python")]
+use analysis::Layout;
+#[cfg(feature = "python")]
 use anyhow::Result;
 #[cfg(feature = "python")]
 use config::Config;
 #[cfg(feature = "python")]
-use fs::crawl_workspace;
-#[cfg(feature = "python")]
-use graph::discover_impacted_nodes;
-#[cfg(feature = "python")]
 use logging::{init_logging, LoggingConfiguration};
 #[cfg(feature = "python")]
-use rustc_hash::{FxHashMap, FxHashSet};
+use rustc_hash::FxHashSet;
+#[cfg(feature = "python")]
+use source::{SourceProvider, WorkingTree};
 #[cfg(feature = "python")]
-use std::path::PathBuf;
+use std::path::Path;
 #[cfg(feature = "python")]
-use utils::{get_python_local_lookup_paths, get_repo_root, merge_hashmaps};
+use utils::get_repo_root;
 
+pub mod analysis;
 pub mod ast;
+pub mod commits;
 pub mod config;
+pub mod cycles;
+pub mod explain;
+pub mod export;
 pub mod fs;
+pub mod git;
 pub mod graph;
+pub mod interpreter;
 pub mod logging;
+pub mod pytest;
+pub mod report;
 pub mod results;
+pub mod selection;
+pub mod source;
 pub mod stdin;
 pub mod utils;
+pub mod workspace;
 
 #[cfg(feature = "python")]
 use pyo3::prelude::*;
@@ -33,7 +45,7 @@ pub fn get_tests(changed_files: Vec<String>) -> PyResult<Vec<String>> {
     let current_dir = std::env::current_dir()?;
     let git_root = get_repo_root(&current_dir)?;
 
-    let config = Config::new(&git_root);
+    let config = Config::new(&git_root)?;
 
     let logging_configuration =
         LoggingConfiguration::new(config.general.verbosity_level, config.general.quiet);
@@ -53,7 +65,7 @@ pub fn get_tests(changed_files: Vec<String>) -> PyResult<Vec<String>> {
     );
     match snob_output {
         Ok(SnobOutput::All) => Ok(vec![]),
-        Ok(SnobOutput::Partial(snob_results)) => Ok(snob_results.impacted.into_iter().collect()),
+        Ok(SnobOutput::Partial(snob_results)) => Ok(snob_results.selected().into_iter().collect()),
         Err(e) => {
             snob_error!("Error: {:?}", e);
             PyResult::Err(PyErr::new::<pyo3::exceptions::PyException, _>(format!(
@@ -81,11 +93,30 @@ pub enum SnobOutput {
 #[cfg(feature = "python")]
 pub fn get_impacted_tests_from_changed_files(
     config: &Config,
-    current_dir: &PathBuf,
-    git_root: &PathBuf,
-    // absolute paths that are guaranteed to exist
+    current_dir: &Path,
+    git_root: &Path,
+    // absolute paths, files that don't exist anymore are considered deleted
     changed: &FxHashSet<String>,
 ) -> Result<SnobOutput> {
+    get_impacted_tests_from_source(config, current_dir, git_root, changed, &WorkingTree, None)
+}
+
+/// Same as `get_impacted_tests_from_changed_files`, analyzing the python files provided by
+/// `source` (e.g. the ones of a git revision) instead of the working tree, optionally along with
+/// the ones of `base_source` (see `graph::build_dependency_graph_from_source`)
+#[cfg(feature = "python")]
+pub fn get_impacted_tests_from_source(
+    config: &Config,
+    current_dir: &Path,
+    git_root: &Path,
+    changed: &FxHashSet<String>,
+    source: &dyn SourceProvider,
+    // when provided, the union of both graphs is traversed
+    base_source: Option<&dyn SourceProvider>,
+) -> Result<SnobOutput> {
+    let deleted_files = fs::find_deleted_files(changed, source);
+    snob_debug!("Deleted files: {:?}", deleted_files);
+
     let run_all_tests_on_change = fs::build_glob_set(&config.files.run_all_tests_on_change)?;
     if utils::should_run_all_tests(changed, &run_all_tests_on_change, git_root) {
         // exit early and run all tests
@@ -93,63 +124,17 @@ pub fn get_impacted_tests_from_changed_files(
         return Ok(SnobOutput::All);
     }
 
-    let lookup_paths = get_python_local_lookup_paths(current_dir, git_root);
-    snob_debug!("Python lookup paths: {:?}", lookup_paths);
-
-    // crawl the target directory
-    let workspace_files = crawl_workspace(current_dir);
-
-    // these need to retain some sort of order information
-    let first_level_components: Vec<PathBuf> = fs::get_first_level_components(&lookup_paths);
-    snob_debug!("First level components: {:?}", first_level_components);
-
-    snob_debug!(
-        "Crawled {:?} files and {:?} directories",
-        workspace_files.len(),
-        first_level_components.len()
-    );
-
-    // keep a copy of the tree (contains all workspace files)
-    let project_files = workspace_files
-        .iter()
-        .map(|p| p.to_string_lossy().to_string())
-        .collect::<FxHashSet<String>>();
-
-    // build dependency graph (remove ignored files)
-    let file_ignores = fs::build_glob_set(&config.files.ignores)?;
-    let mut all_file_imports: Vec<FxHashMap<String, Vec<String>>> = graph::build_dependency_graph(
-        &workspace_files,
-        &project_files,
-        &file_ignores,
-        &first_level_components,
-        git_root,
-    );
-
-    // not deduplicated
-    let dependency_graph = utils::deduplicate_dependencies(merge_hashmaps(&mut all_file_imports));
-    snob_debug!("Dependency graph:");
-    for (k, v) in &dependency_graph {
-        snob_debug!("\t{k} is used by:");
-        v.iter().for_each(|v| snob_debug!("\t\t{v}"));
-    }
-
-    let impacted_nodes: FxHashSet<String> = discover_impacted_nodes(&dependency_graph, changed);
-
-    // filter impacted nodes to get the tests
-    // test_*.py   or   *_test.py
-    let ignored_tests = fs::build_glob_set(&config.tests.ignores)?;
-    let tests_to_always_run = fs::build_glob_set(&config.tests.always_run)?;
-
-    let snob_results = results::SnobResult::new(
-        impacted_nodes,
-        project_files.clone(),
-        &ignored_tests,
-        &tests_to_always_run,
+    let layout = Layout::new(git_root, current_dir, current_dir, None)?;
+    let selection = selection::select(
+        config,
+        &layout,
         git_root,
-    );
-    snob_debug!(" impacted tests: {:?}", snob_results.impacted);
-    snob_debug!(" ignored tests: {:?}", snob_results.ignored);
-    snob_debug!(" always run tests: {:?}", snob_results.always_run);
-
-    Ok(SnobOutput::Partial(snob_results))
+        source,
+        base_source,
+        changed,
+        &deleted_files,
+        false,
+    )?;
+
+    Ok(SnobOutput::Partial(selection.results))
 }
@@ -1,20 +1,35 @@
-use rustc_hash::{FxHashMap, FxHashSet};
+use rustc_hash::FxHashSet;
 use std::io::{BufWriter, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::Result;
 
 use clap::Parser;
 
+use source::SourceProvider;
+use utils::relative_to;
+
+mod analysis;
 mod ast;
 mod cli;
+mod commits;
 mod config;
+mod cycles;
+mod explain;
+mod export;
 mod fs;
+mod git;
 mod graph;
+mod interpreter;
 mod logging;
+mod pytest;
+mod report;
 mod results;
+mod selection;
+mod source;
 mod stdin;
 mod utils;
+mod workspace;
 
 fn main() -> Result<()> {
     let cli = cli::Cli::parse();
@@ -25,116 +40,285 @@ fn main() -> Result<()> {
     ));
 
     let current_dir = std::env::current_dir()?;
-    let git_root = utils::get_repo_root(&current_dir)?;
+    let git_root = match &cli.root {
+        // the crawled paths are compared with the root, they don't have `..` components
+        Some(root) => utils::normalize_path(&std::path::absolute(current_dir.join(root))?),
+        None => utils::get_repo_root(&current_dir)?,
+    };
     snob_debug!("Git root: {:?}", git_root);
 
-    let config = config::Config::new(&git_root);
+    let config = config::Config::load(&git_root, cli.config.as_deref())?;
     snob_debug!("Config: {:?}", config);
 
+    // subcommands take their own changed files
+    if cli.command.is_some() && (!cli.change_selection().is_empty() || cli.revision.is_some()) {
+        anyhow::bail!(
+            "`--base`, `--staged`, `--unstaged`, `--untracked` and `--revision` only apply to the \
+             selection of tests, not to subcommands"
+        );
+    }
+
+    match &cli.command {
+        Some(cli::Command::Config(cli::ConfigCommand::Check)) => {
+            print!("{}", config.to_toml()?);
+            Ok(())
+        }
+        Some(cli::Command::Commits { range }) => {
+            print_commits(&cli, &config, &current_dir, &git_root, range)
+        }
+        Some(cli::Command::BlameRange { test, range }) => {
+            print_blame_range(&cli, &config, &current_dir, &git_root, test, range)
+        }
+        Some(cli::Command::Why {
+            test,
+            changed_files,
+        }) => print_why(&cli, &config, &current_dir, &git_root, test, changed_files),
+        Some(cli::Command::Deps { file, depth }) => {
+            print_dependencies(&cli, &config, &current_dir, &git_root, file, *depth, false)
+        }
+        Some(cli::Command::Rdeps { file, depth }) => {
+            print_dependencies(&cli, &config, &current_dir, &git_root, file, *depth, true)
+        }
+        Some(cli::Command::WhyNot {
+            test,
+            changed_files,
+        }) => print_why_not(&cli, &config, &current_dir, &git_root, test, changed_files),
+        Some(cli::Command::Report {
+            html,
+            changed_files,
+        }) => write_report(&cli, &config, &current_dir, &git_root, html, changed_files),
+        Some(cli::Command::Cycles {
+            baseline,
+            update_baseline,
+        }) => print_cycles(
+            &cli,
+            &config,
+            &current_dir,
+            &git_root,
+            baseline.as_deref(),
+            *update_baseline,
+        ),
+        None => select_tests(cli, &config, &current_dir, &git_root),
+    }
+}
+
+fn select_tests(
+    cli: cli::Cli,
+    config: &config::Config,
+    current_dir: &Path,
+    git_root: &Path,
+) -> Result<()> {
+    if (cli.nul_separated || cli.name_status) && !cli.reads_stdin() {
+        anyhow::bail!(
+            "`-z`/`--null` and `--name-status` describe the changed files read from stdin, \
+             which requires `-` (or `--stdin`)"
+        );
+    }
+    // clap can't tell the values of `--emit` apart
+    match cli.emit {
+        cli::Emit::Tests if cli.exclude_tests || !cli.modules_glob.is_empty() => {
+            anyhow::bail!("`--exclude-tests` and `--modules-glob` require `--emit modules`")
+        }
+        cli::Emit::Modules if cli.format == cli::OutputFormat::Json || cli.group_by_member => {
+            anyhow::bail!(
+                "`--emit modules` prints one file per line, it can't be combined with \
+                 `--format json` or `--group-by-member`"
+            )
+        }
+        _ => {}
+    }
+
     // files that were modified by the range of commits
-    let input_files = if stdin::is_readable_stdin() {
-        stdin::read_from_stdin()
+    let change_selection = cli.change_selection();
+    let input_files = if !change_selection.is_empty() {
+        git::get_changed_files(git_root, &change_selection)?
+            .into_iter()
+            .map(|path| git::FileChange {
+                path,
+                deleted: false,
+            })
+            .collect()
     } else {
-        cli.updated_files
+        let mut input_files = cli
+            .updated_files
+            .iter()
+            .filter(|f| *f != cli::STDIN_ARG)
+            .map(|path| git::FileChange {
+                path: path.clone(),
+                deleted: false,
+            })
+            .collect::<Vec<_>>();
+        if cli.reads_stdin() {
+            input_files.extend(stdin::read_from_stdin(cli.input_format())?);
+        } else if cli.updated_files.is_empty() && stdin::is_readable_stdin() {
+            snob_warn!(
+                "Reading the changed files from stdin without `-` (or `--stdin`) is deprecated \
+                 and will stop working in a future version"
+            );
+            input_files.extend(stdin::read_from_stdin(cli.input_format())?);
+        }
+        input_files
     };
-    let updated_files = fs::make_files_relative_to(&input_files, &current_dir)
-        .iter()
-        .cloned()
-        .collect::<FxHashSet<String>>();
+    let updated_files = fs::make_files_relative_to(
+        &input_files.iter().map(|f| &f.path).collect::<Vec<_>>(),
+        current_dir,
+    )
+    .into_iter()
+    .collect::<FxHashSet<String>>();
     snob_debug!("Updated files: {:?}", updated_files);
+    let deleted_input_files = fs::make_files_relative_to(
+        &input_files
+            .iter()
+            .filter(|f| f.deleted)
+            .map(|f| &f.path)
+            .collect::<Vec<_>>(),
+        current_dir,
+    );
 
-    fs::check_files_exist(&updated_files)?;
+    let source: Box<dyn SourceProvider> = match &cli.revision {
+        Some(revision) => Box::new(source::GitRevision::new(git_root, revision)?),
+        None => Box::new(source::WorkingTree),
+    };
+    // the graph of the merge base brings back the imports removed by the changes
+    let base_source = match (&cli.base, cli.include_base_graph) {
+        (Some(base), true) => {
+            let merge_base = git::merge_base(git_root, base, change_selection.revision())?;
+            Some(source::GitRevision::new(git_root, &merge_base)?)
+        }
+        _ => None,
+    };
+    // deleted (or renamed) files are kept: their former consumers are impacted as well
+    let mut deleted_files = fs::find_deleted_files(&updated_files, source.as_ref());
+    // git reports the files it compares, the other missing paths may as well be typos
+    if change_selection.is_empty() {
+        for file in deleted_files
+            .iter()
+            .filter(|f| !deleted_input_files.contains(f))
+        {
+            snob_warn!(
+                "{} does not exist and was not reported as deleted, considering it deleted",
+                file
+            );
+        }
+    }
+    deleted_files.extend(deleted_input_files);
+    snob_debug!("Deleted files: {:?}", deleted_files);
 
     let run_all_tests_on_change = fs::build_glob_set(&config.files.run_all_tests_on_change)?;
-    if utils::should_run_all_tests(&updated_files, &run_all_tests_on_change, &git_root) {
+    let run_all = utils::should_run_all_tests(&updated_files, &run_all_tests_on_change, git_root);
+    // the emitted modules and the exported graphs still need the project to be analyzed
+    if run_all
+        && cli.emit == cli::Emit::Tests
+        && cli.export_graph.is_none()
+        && cli.dot_graph.is_none()
+    {
         // exit early and run all tests
-        snob_info!("Running all tests");
-        println!(".");
-        return Ok(());
+        let mut writer = BufWriter::new(std::io::stdout().lock());
+        write_all_tests(&cli, &updated_files, &mut writer)?;
+        return Ok(writer.flush()?);
     }
 
-    std::env::set_current_dir(&cli.target_directory)?;
+    std::env::set_current_dir(current_dir.join(&cli.target_directory))?;
     snob_debug!("Current directory: {:?}", current_dir);
-    let lookup_paths = utils::get_python_local_lookup_paths(&current_dir, &git_root);
-    snob_debug!("Python lookup paths: {:?}", lookup_paths);
+    let layout = layout(&cli, current_dir, git_root)?;
 
     // FIXME: we might want to measure time differently here
     let instant = std::time::Instant::now();
 
-    // crawl the target directory
-    let workspace_files = fs::crawl_workspace(&current_dir);
-
-    // these need to retain some sort of order information
-    let first_level_components: Vec<PathBuf> = fs::get_first_level_components(&lookup_paths);
-
-    snob_debug!("First level components: {:?}", first_level_components);
-
-    snob_debug!(
-        "Crawled {:?} files and {:?} directories",
-        workspace_files.len(),
-        first_level_components.len()
-    );
-
-    // keep a copy of the tree (contains all workspace files)
-    let project_files = workspace_files
-        .iter()
-        .map(|p| p.to_string_lossy().to_string())
-        .collect::<FxHashSet<String>>();
-
-    // build dependency graph (remove ignored files)
-    let file_ignores = fs::build_glob_set(&config.files.ignores)?;
-    let mut all_file_imports: Vec<FxHashMap<String, Vec<String>>> = graph::build_dependency_graph(
-        &workspace_files,
-        &project_files,
-        &file_ignores,
-        &first_level_components,
-        &git_root,
-    );
-
-    // not deduplicated
-    let dependency_graph =
-        utils::deduplicate_dependencies(utils::merge_hashmaps(&mut all_file_imports));
+    let selection::Selection {
+        analysis,
+        predecessors,
+        impacted_nodes,
+        test_matcher,
+        results: snob_results,
+    } = selection::select(
+        config,
+        &layout,
+        git_root,
+        source.as_ref(),
+        base_source.as_ref().map(|s| s as &dyn SourceProvider),
+        &updated_files,
+        &deleted_files,
+        run_all,
+    )?;
 
-    snob_debug!("Dependency graph:");
-    for (k, v) in &dependency_graph {
-        snob_debug!("\t{k} is used by:");
-        v.iter().for_each(|v| snob_debug!("\t\t{v}"));
+    if let Some(export_path) = &cli.export_graph {
+        let format = match cli.export_format {
+            Some(format) => format.into(),
+            None => export::GraphFormat::from_extension(export_path).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Can't guess the format of {} from its extension, use --export-format",
+                    export_path.display()
+                )
+            })?,
+        };
+        let files = if cli.export_impacted_only {
+            // changed files may not be python files
+            impacted_nodes
+                .iter()
+                .filter(|f| analysis.project_files.contains(*f))
+                .cloned()
+                .collect()
+        } else {
+            analysis.project_files.clone()
+        };
+        let exported_graph = export::ExportedGraph::new(
+            &analysis,
+            &files,
+            &updated_files,
+            &test_matcher,
+            &layout.lookup_paths,
+            git_root,
+        );
+        let mut writer = BufWriter::new(std::fs::File::create(export_path)?);
+        exported_graph.write(format, &mut writer)?;
+        writer.flush()?;
     }
 
-    let impacted_nodes: FxHashSet<String> = if let Some(dot_graph) = &cli.dot_graph {
-        graph::discover_impacted_nodes_with_graphviz(&dependency_graph, &updated_files, dot_graph)
+    let impacted_modules = if cli.emit == cli::Emit::Modules {
+        let modules_glob = fs::build_glob_set(&cli.modules_glob.iter().cloned().collect())?;
+        Some(results::impacted_modules(
+            &impacted_nodes,
+            &analysis.project_files,
+            cli.exclude_tests.then_some(&test_matcher),
+            (!cli.modules_glob.is_empty()).then_some(&modules_glob),
+            git_root,
+        ))
     } else {
-        graph::discover_impacted_nodes(&dependency_graph, &updated_files)
+        None
     };
 
-    // filter impacted nodes to get the tests
-    // just like `pytest` we consider files that look like either test_*.py or *_test.py
-    // see https://docs.pytest.org/en/stable/explanation/goodpractices.html#conventions-for-python-test-discovery
-    let ignored_tests = fs::build_glob_set(&config.tests.ignores)?;
-    let tests_to_always_run = fs::build_glob_set(&config.tests.always_run)?;
-
-    let snob_results = results::SnobResult::new(
-        impacted_nodes,
-        project_files.clone(),
-        &ignored_tests,
-        &tests_to_always_run,
-        &git_root,
-    );
-    snob_debug!(" impacted tests: {:?}", snob_results.impacted);
-    snob_debug!(" ignored tests: {:?}", snob_results.ignored);
-    snob_debug!(" always run tests: {:?}", snob_results.always_run);
+    if let Some(dot_graph) = &cli.dot_graph {
+        let impacted_graph = export::ExportedGraph::new(
+            &analysis,
+            predecessors.nodes(),
+            &updated_files,
+            &test_matcher,
+            &layout.lookup_paths,
+            git_root,
+        );
+        let selected = snob_results
+            .selected()
+            .iter()
+            .map(|test| relative_to(test, git_root))
+            .collect();
+        let mut writer = BufWriter::new(std::fs::File::create(dot_graph)?);
+        impacted_graph.write_dot(&selected, cli.dot_collapse_threshold, &mut writer)?;
+        writer.flush()?;
+    }
 
     snob_info!(
         "Analyzed {:?} files in {:?}",
-        workspace_files.len(),
+        analysis.workspace_files.len(),
         instant.elapsed()
     );
     snob_info!(
         "Found {}/{} impacted tests",
         snob_results.impacted.len(),
-        workspace_files
+        analysis
+            .workspace_files
             .iter()
-            .filter(|f| utils::is_test_file(f))
+            .filter(|f| test_matcher.is_test_file(f))
             .collect::<Vec<_>>()
             .len()
     );
@@ -143,11 +327,489 @@ fn main() -> Result<()> {
     let stdout = std::io::stdout().lock();
     let mut writer = BufWriter::new(stdout);
 
-    for test in snob_results.impacted {
-        writeln!(writer, "{test}")?;
+    if let Some(modules) = impacted_modules {
+        for module in modules {
+            writeln!(writer, "{module}")?;
+        }
+    } else if run_all {
+        write_all_tests(&cli, &updated_files, &mut writer)?;
+    } else if cli.format == cli::OutputFormat::Json {
+        let report = results::SelectionReport::new(&updated_files, &snob_results, &predecessors);
+        serde_json::to_writer_pretty(&mut writer, &report)?;
+        writeln!(writer)?;
+    } else if cli.group_by_member {
+        let groups = workspace::group_by_member(
+            &snob_results.selected(),
+            &layout.workspace_members,
+            git_root,
+        );
+        for (member_root, tests) in groups {
+            let member_dir = match member_root.strip_prefix(git_root) {
+                Ok(p) if p.as_os_str().is_empty() => PathBuf::from("."),
+                Ok(p) => p.to_path_buf(),
+                Err(_) => member_root,
+            };
+            for test in tests {
+                writeln!(writer, "{}\t{}", member_dir.display(), test.display())?;
+            }
+        }
+    } else {
+        for test in snob_results.selected() {
+            writeln!(writer, "{test}")?;
+        }
     }
 
     writer.flush()?;
 
     Ok(())
 }
+
+fn print_commits(
+    cli: &cli::Cli,
+    config: &config::Config,
+    current_dir: &Path,
+    git_root: &Path,
+    range: &str,
+) -> Result<()> {
+    let layout = layout(cli, current_dir, git_root)?;
+    let impacts = commits::impacted_tests_per_commit(config, &layout, git_root, range)?;
+
+    let write_impacted = |writer: &mut BufWriter<_>, impacted: Option<&_>| -> Result<()> {
+        match impacted {
+            None => writeln!(writer, "  impacted .")?,
+            Some(impacted) => {
+                for test in impacted {
+                    writeln!(writer, "  impacted {}", relative_to(test, git_root))?;
+                }
+            }
+        }
+        Ok(())
+    };
+
+    let stdout = std::io::stdout().lock();
+    let mut writer = BufWriter::new(stdout);
+    for impact in &impacts {
+        writeln!(writer, "commit {} {}", impact.commit, impact.summary)?;
+        for file in &impact.changed {
+            writeln!(writer, "  changed {}", relative_to(file, git_root))?;
+        }
+        write_impacted(&mut writer, impact.impacted.as_ref())?;
+        writeln!(writer)?;
+    }
+    writeln!(writer, "union")?;
+    write_impacted(&mut writer, commits::union(&impacts).as_ref())?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn print_blame_range(
+    cli: &cli::Cli,
+    config: &config::Config,
+    current_dir: &Path,
+    git_root: &Path,
+    test: &Path,
+    range: &str,
+) -> Result<()> {
+    let layout = layout(cli, current_dir, git_root)?;
+    let test = std::path::absolute(current_dir.join(test))?;
+    let commits =
+        commits::commits_touching_test(config, &layout, git_root, &test.to_string_lossy(), range)?;
+    snob_info!("Found {} candidate commits", commits.len());
+
+    let stdout = std::io::stdout().lock();
+    let mut writer = BufWriter::new(stdout);
+    for commit in commits {
+        writeln!(writer, "commit {} {}", commit.commit, commit.summary)?;
+        for file in &commit.touched {
+            writeln!(writer, "  touched {}", relative_to(file, git_root))?;
+        }
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+// the test, changed files and analysis `snob why` and `snob why-not` look at
+fn analyze_for_explanation(
+    cli: &cli::Cli,
+    config: &config::Config,
+    current_dir: &Path,
+    git_root: &Path,
+    test: &Path,
+    changed_files: &[String],
+) -> Result<(String, FxHashSet<String>, analysis::Analysis)> {
+    let changed_files = changed_or_uncommitted_files(current_dir, git_root, changed_files)?;
+    let test = std::path::absolute(current_dir.join(test))?
+        .to_string_lossy()
+        .to_string();
+
+    let deleted_files = fs::find_deleted_files(&changed_files, &source::WorkingTree);
+    let analysis = analyze_working_tree(cli, config, current_dir, git_root, &deleted_files)?;
+    Ok((test, changed_files, analysis))
+}
+
+// the given changed files, or the uncommitted changes of the repository when there are none
+fn changed_or_uncommitted_files(
+    current_dir: &Path,
+    git_root: &Path,
+    changed_files: &[String],
+) -> Result<FxHashSet<String>> {
+    let changed_files = if changed_files.is_empty() {
+        git::get_changed_files(
+            git_root,
+            &git::ChangeSelection {
+                staged: true,
+                unstaged: true,
+                untracked: true,
+                ..Default::default()
+            },
+        )?
+    } else {
+        fs::make_files_relative_to(changed_files, current_dir)
+    };
+    Ok(changed_files.into_iter().collect())
+}
+
+// a changed file triggers all tests
+fn write_all_tests(
+    cli: &cli::Cli,
+    updated_files: &FxHashSet<String>,
+    writer: &mut impl Write,
+) -> Result<()> {
+    snob_info!("Running all tests");
+    if cli.format == cli::OutputFormat::Json {
+        let report = results::SelectionReport::all(updated_files);
+        serde_json::to_writer_pretty(&mut *writer, &report)?;
+        writeln!(writer)?;
+    } else {
+        writeln!(writer, ".")?;
+    }
+    Ok(())
+}
+
+// the directories every command crawls and resolves imports from
+fn layout(cli: &cli::Cli, current_dir: &Path, git_root: &Path) -> Result<analysis::Layout> {
+    let target_directory = utils::normalize_path(&std::path::absolute(
+        current_dir.join(&cli.target_directory),
+    )?);
+    snob_debug!("Target directory: {:?}", target_directory);
+    analysis::Layout::new(
+        git_root,
+        current_dir,
+        &target_directory,
+        cli.python.as_deref(),
+    )
+}
+
+fn analyze_working_tree(
+    cli: &cli::Cli,
+    config: &config::Config,
+    current_dir: &Path,
+    git_root: &Path,
+    deleted_files: &FxHashSet<String>,
+) -> Result<analysis::Analysis> {
+    let layout = layout(cli, current_dir, git_root)?;
+    analysis::Analysis::new(
+        &source::WorkingTree,
+        &layout,
+        config,
+        git_root,
+        deleted_files,
+        None,
+    )
+}
+
+fn print_dependencies(
+    cli: &cli::Cli,
+    config: &config::Config,
+    current_dir: &Path,
+    git_root: &Path,
+    file: &Path,
+    depth: Option<usize>,
+    reverse: bool,
+) -> Result<()> {
+    let analysis = analyze_working_tree(cli, config, current_dir, git_root, &FxHashSet::default())?;
+    let file = std::path::absolute(current_dir.join(file))?
+        .to_string_lossy()
+        .to_string();
+    if !analysis.project_files.contains(&file) {
+        anyhow::bail!("{file} is not part of the analyzed files");
+    }
+
+    let files = if reverse {
+        graph::dependents_of(&analysis.dependency_graph, &file, depth)
+    } else {
+        graph::dependencies_of(&analysis.dependency_graph, &file, depth)
+    };
+
+    let stdout = std::io::stdout().lock();
+    let mut writer = BufWriter::new(stdout);
+    for (file, _) in files {
+        writeln!(writer, "{}", relative_to(file, git_root))?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn print_why(
+    cli: &cli::Cli,
+    config: &config::Config,
+    current_dir: &Path,
+    git_root: &Path,
+    test: &Path,
+    changed_files: &[String],
+) -> Result<()> {
+    let (test, changed_files, analysis) =
+        analyze_for_explanation(cli, config, current_dir, git_root, test, changed_files)?;
+
+    let stdout = std::io::stdout().lock();
+    let mut writer = BufWriter::new(stdout);
+
+    let run_all_tests_on_change = fs::build_glob_set(&config.files.run_all_tests_on_change)?;
+    if utils::should_run_all_tests(&changed_files, &run_all_tests_on_change, git_root) {
+        writeln!(
+            writer,
+            "{} is selected: a changed file matches `files.run-all-tests-on-change`",
+            relative_to(&test, git_root)
+        )?;
+    }
+    let always_run = fs::build_glob_set(&config.tests.always_run)?;
+    if always_run.is_match(relative_to(&test, git_root)) {
+        writeln!(
+            writer,
+            "{} is selected: it matches `tests.always-run`",
+            relative_to(&test, git_root)
+        )?;
+    }
+
+    let chains = explain::why(&analysis, &test, &changed_files);
+    if chains.is_empty() {
+        writeln!(
+            writer,
+            "{} does not depend on any of the changed files",
+            relative_to(&test, git_root)
+        )?;
+    }
+    for (i, chain) in chains.into_iter().enumerate() {
+        if i > 0 {
+            writeln!(writer)?;
+        }
+        for step in chain {
+            match step.site {
+                None => writeln!(writer, "{}", relative_to(&step.file, git_root))?,
+                Some(site) => {
+                    let statement = source::WorkingTree
+                        .read_to_string(Path::new(&step.file))
+                        .ok()
+                        .and_then(|contents| {
+                            contents
+                                .lines()
+                                .nth(site.line - 1)
+                                .map(|l| l.trim().to_string())
+                        })
+                        .unwrap_or_default();
+                    writeln!(
+                        writer,
+                        "  imported by {}:{} ({}) {}",
+                        relative_to(&step.file, git_root),
+                        site.line,
+                        site.kind,
+                        statement
+                    )?;
+                }
+            }
+        }
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn print_why_not(
+    cli: &cli::Cli,
+    config: &config::Config,
+    current_dir: &Path,
+    git_root: &Path,
+    test: &Path,
+    changed_files: &[String],
+) -> Result<()> {
+    let (test, changed_files, analysis) =
+        analyze_for_explanation(cli, config, current_dir, git_root, test, changed_files)?;
+    let test_matcher = pytest::TestFileMatcher::new(config, git_root)?
+        .with_test_targets(analysis.test_targets.clone());
+    let why_not = explain::why_not(
+        &analysis,
+        config,
+        &test_matcher,
+        git_root,
+        &test,
+        &changed_files,
+    )?;
+
+    let stdout = std::io::stdout().lock();
+    let mut writer = BufWriter::new(stdout);
+
+    let test = relative_to(&test, git_root);
+    if why_not.not_analyzed {
+        writeln!(
+            writer,
+            "{test} was not found among the analyzed files (check `--target-directory` and the lookup paths)"
+        )?;
+    }
+    if why_not.not_a_test {
+        writeln!(
+            writer,
+            "{test} is not a test file (see `python_files`, `testpaths` and `norecursedirs`)"
+        )?;
+    }
+    if why_not.ignored_test {
+        writeln!(writer, "{test} is ignored (it matches `tests.ignores`)")?;
+    }
+    if why_not.impacted_by.is_empty() {
+        writeln!(writer, "{test} does not depend on any of the changed files")?;
+    } else {
+        writeln!(writer, "{test} depends on changed files (see `snob why`):")?;
+        for file in &why_not.impacted_by {
+            writeln!(writer, "  {}", relative_to(file, git_root))?;
+        }
+    }
+
+    if !why_not.ignored_files.is_empty() {
+        writeln!(
+            writer,
+            "\nIgnored files (`files.ignores`), whose imports are not followed:"
+        )?;
+        for file in &why_not.ignored_files {
+            writeln!(writer, "  {}", relative_to(file, git_root))?;
+        }
+    }
+    if !why_not.unresolved_imports.is_empty() {
+        writeln!(writer, "\nUnresolved imports:")?;
+        for (file, import) in &why_not.unresolved_imports {
+            writeln!(
+                writer,
+                "  {}:{} {}",
+                relative_to(file, git_root),
+                import.site.line,
+                import.import
+            )?;
+        }
+    }
+    if !why_not.parse_failures.is_empty() {
+        writeln!(
+            writer,
+            "\nFiles that could not be parsed, whose imports are unknown:"
+        )?;
+        for file in &why_not.parse_failures {
+            writeln!(writer, "  {}", relative_to(file, git_root))?;
+        }
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn write_report(
+    cli: &cli::Cli,
+    config: &config::Config,
+    current_dir: &Path,
+    git_root: &Path,
+    html: &Path,
+    changed_files: &[String],
+) -> Result<()> {
+    let changed_files = changed_or_uncommitted_files(current_dir, git_root, changed_files)?;
+    let deleted_files = fs::find_deleted_files(&changed_files, &source::WorkingTree);
+    let layout = layout(cli, current_dir, git_root)?;
+    // the report lists the tests `snob` would select
+    let run_all_tests_on_change = fs::build_glob_set(&config.files.run_all_tests_on_change)?;
+    let run_all = utils::should_run_all_tests(&changed_files, &run_all_tests_on_change, git_root);
+    let selection = selection::select(
+        config,
+        &layout,
+        git_root,
+        &source::WorkingTree,
+        None,
+        &changed_files,
+        &deleted_files,
+        run_all,
+    )?;
+
+    let mut report = report::Report::new(
+        &selection.analysis,
+        &selection.predecessors,
+        &changed_files,
+        &selection.results,
+        &selection.test_matcher,
+        &layout.lookup_paths,
+        git_root,
+    );
+    report.summary.run_all = run_all;
+
+    let mut writer = BufWriter::new(std::fs::File::create(html)?);
+    report.write_html(&mut writer)?;
+    writer.flush()?;
+    snob_info!("Wrote the report to {}", html.display());
+
+    Ok(())
+}
+
+fn print_cycles(
+    cli: &cli::Cli,
+    config: &config::Config,
+    current_dir: &Path,
+    git_root: &Path,
+    baseline: Option<&Path>,
+    update_baseline: bool,
+) -> Result<()> {
+    let analysis = analyze_working_tree(cli, config, current_dir, git_root, &FxHashSet::default())?;
+    let import_cycles = cycles::find_import_cycles(&analysis);
+    snob_info!("Found {} import cycles", import_cycles.len());
+
+    if let (Some(baseline), true) = (baseline, update_baseline) {
+        cycles::write_baseline(baseline, &import_cycles, git_root)?;
+        snob_info!("Wrote the cycles baseline to {}", baseline.display());
+        return Ok(());
+    }
+    let new_cycles = match baseline {
+        Some(baseline) => {
+            let known = cycles::read_baseline(baseline)?;
+            cycles::new_cycles(&import_cycles, &known, git_root)
+        }
+        None => FxHashSet::default(),
+    };
+
+    let stdout = std::io::stdout().lock();
+    let mut writer = BufWriter::new(stdout);
+    for (i, cycle) in import_cycles.iter().enumerate() {
+        if i > 0 {
+            writeln!(writer)?;
+        }
+        let new = if new_cycles.contains(&i) {
+            "New import cycle"
+        } else {
+            "Import cycle"
+        };
+        writeln!(writer, "{new} between {} files:", cycle.files.len())?;
+        for (importer, imported, site) in &cycle.imports {
+            writeln!(
+                writer,
+                "  {}:{} ({}) imports {}",
+                relative_to(importer, git_root),
+                site.line,
+                site.kind,
+                relative_to(imported, git_root)
+            )?;
+        }
+    }
+    writer.flush()?;
+
+    if !new_cycles.is_empty() {
+        anyhow::bail!(
+            "{} import cycles are not part of the baseline",
+            new_cycles.len()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,286 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use rustc_hash::FxHashSet;
+
+use crate::config::Config;
+use crate::snob_debug;
+use crate::utils::is_test_file;
+
+// see https://docs.pytest.org/en/stable/reference/customize.html#configuration-file-formats
+// (in order of precedence)
+const PYTEST_INI: &str = "pytest.ini";
+const PYTEST_INI_HIDDEN: &str = ".pytest.ini";
+const PYPROJECT: &str = "pyproject.toml";
+const TOX_INI: &str = "tox.ini";
+const SETUP_CFG: &str = "setup.cfg";
+
+// see https://docs.pytest.org/en/stable/reference/reference.html#confval-norecursedirs
+const DEFAULT_NORECURSEDIRS: [&str; 9] = [
+    "*.egg",
+    ".*",
+    "_darcs",
+    "build",
+    "CVS",
+    "dist",
+    "node_modules",
+    "venv",
+    "{arch}",
+];
+
+/// The subset of pytest's settings that drive test discovery
+#[derive(Debug, Default, PartialEq)]
+pub struct PytestSettings {
+    pub python_files: Option<Vec<String>>,
+    pub testpaths: Option<Vec<String>>,
+    pub norecursedirs: Option<Vec<String>>,
+}
+
+impl PytestSettings {
+    /// Read pytest's settings from the first configuration file pytest itself would pick
+    /// in `root_dir`
+    pub fn read(root_dir: &Path) -> Self {
+        for (file, section) in [
+            (PYTEST_INI, "pytest"),
+            (PYTEST_INI_HIDDEN, "pytest"),
+            (PYPROJECT, ""),
+            (TOX_INI, "pytest"),
+            (SETUP_CFG, "tool:pytest"),
+        ] {
+            let Ok(contents) = std::fs::read_to_string(root_dir.join(file)) else {
+                continue;
+            };
+            let settings = if file == PYPROJECT {
+                Self::from_pyproject(&contents)
+            } else {
+                Self::from_ini(&contents, section)
+            };
+            if let Some(settings) = settings {
+                snob_debug!("Found pytest settings in {:?}", root_dir.join(file));
+                return settings;
+            }
+        }
+        Self::default()
+    }
+
+    // `[tool.pytest.ini_options]`, values can either be arrays or whitespace separated strings
+    fn from_pyproject(contents: &str) -> Option<Self> {
+        let pyproject: toml::Value = toml::from_str(contents).ok()?;
+        let options = pyproject.get("tool")?.get("pytest")?.get("ini_options")?;
+        let get = |key: &str| {
+            options.get(key).map(|value| match value {
+                toml::Value::Array(values) => values
+                    .iter()
+                    .filter_map(|v| v.as_str())
+                    .map(std::string::ToString::to_string)
+                    .collect(),
+                toml::Value::String(value) => split_ini_value(value),
+                _ => Vec::new(),
+            })
+        };
+        Some(Self {
+            python_files: get("python_files"),
+            testpaths: get("testpaths"),
+            norecursedirs: get("norecursedirs"),
+        })
+    }
+
+    // minimal ini reader: values may span several lines as long as continuation lines are indented
+    fn from_ini(contents: &str, section: &str) -> Option<Self> {
+        let mut in_section = false;
+        let mut found_section = false;
+        let mut settings = Self::default();
+        let mut current_key: Option<&str> = None;
+
+        for line in contents.lines() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with(';') {
+                continue;
+            }
+            if let Some(name) = trimmed.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                in_section = name.trim() == section;
+                found_section |= in_section;
+                current_key = None;
+                continue;
+            }
+            if !in_section {
+                continue;
+            }
+            if line.starts_with(char::is_whitespace) {
+                if let Some(Some(values)) = current_key.and_then(|k| settings.field(k)) {
+                    values.extend(split_ini_value(trimmed));
+                }
+                continue;
+            }
+            let Some((key, value)) = trimmed.split_once('=').or_else(|| trimmed.split_once(':'))
+            else {
+                continue;
+            };
+            current_key = Some(key.trim());
+            if let Some(field) = settings.field(key.trim()) {
+                *field = Some(split_ini_value(value));
+            }
+        }
+
+        found_section.then_some(settings)
+    }
+
+    fn field(&mut self, key: &str) -> Option<&mut Option<Vec<String>>> {
+        match key {
+            "python_files" => Some(&mut self.python_files),
+            "testpaths" => Some(&mut self.testpaths),
+            "norecursedirs" => Some(&mut self.norecursedirs),
+            _ => None,
+        }
+    }
+}
+
+fn split_ini_value(value: &str) -> Vec<String> {
+    value
+        .split_whitespace()
+        .map(|v| v.trim_matches(|c| c == '"' || c == '\'').to_string())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+/// Decides which files are test files, following pytest's discovery rules
+/// (`python_files`, `testpaths` and `norecursedirs`).
+///
+/// Settings defined in snob's configuration take precedence over pytest's ones.
+/// Files recognized as tests by their AST (see `ast::TestDetection`) are always test files.
+#[derive(Debug)]
+pub struct TestFileMatcher {
+    root_dir: PathBuf,
+    test_targets: FxHashSet<String>,
+    /// patterns without a path separator are matched against file names, the others
+    /// against full paths (just like pytest does)
+    python_files: Option<(GlobSet, GlobSet)>,
+    testpaths: Vec<PathBuf>,
+    /// directory -> its testpaths, replacing `testpaths` below it (see `TestsConfig`)
+    nested_testpaths: Vec<(PathBuf, Vec<PathBuf>)>,
+    norecursedirs: GlobSet,
+}
+
+impl TestFileMatcher {
+    pub fn new(config: &Config, root_dir: &Path) -> Result<Self> {
+        let pytest = PytestSettings::read(root_dir);
+        let python_files = config.tests.python_files.clone().or(pytest.python_files);
+        let testpaths = config.tests.testpaths.clone().or(pytest.testpaths);
+        let norecursedirs = config
+            .tests
+            .norecursedirs
+            .clone()
+            .or(pytest.norecursedirs)
+            .unwrap_or_else(|| {
+                DEFAULT_NORECURSEDIRS
+                    .iter()
+                    .map(std::string::ToString::to_string)
+                    .collect()
+            });
+        snob_debug!(
+            "Test files: python_files={:?} testpaths={:?} norecursedirs={:?}",
+            python_files,
+            testpaths,
+            norecursedirs
+        );
+
+        let python_files = match python_files {
+            Some(patterns) => {
+                let (path_patterns, name_patterns): (Vec<_>, Vec<_>) =
+                    patterns.iter().partition(|p| p.contains('/'));
+                Some((
+                    build_fnmatch_set(name_patterns)?,
+                    build_fnmatch_set(path_patterns.iter().map(|p| format!("*/{p}")))?,
+                ))
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            root_dir: root_dir.to_path_buf(),
+            test_targets: FxHashSet::default(),
+            python_files,
+            testpaths: testpaths
+                .unwrap_or_default()
+                .iter()
+                .map(|p| root_dir.join(p))
+                .collect(),
+            nested_testpaths: config
+                .tests
+                .nested_testpaths
+                .iter()
+                .map(|(dir, testpaths)| {
+                    (
+                        root_dir.join(dir),
+                        testpaths.iter().map(|p| root_dir.join(p)).collect(),
+                    )
+                })
+                .collect(),
+            norecursedirs: build_fnmatch_set(&norecursedirs)?,
+        })
+    }
+
+    /// Files found to be tests while building the dependency graph
+    pub fn with_test_targets(mut self, test_targets: FxHashSet<String>) -> Self {
+        self.test_targets = test_targets;
+        self
+    }
+
+    pub fn is_test_file<P>(&self, file: P) -> bool
+    where
+        P: AsRef<Path>,
+    {
+        let file = file.as_ref();
+        if self.test_targets.contains(file.to_string_lossy().as_ref()) {
+            return true;
+        }
+        let matches_python_files = match &self.python_files {
+            Some((name_patterns, path_patterns)) => {
+                file.file_name()
+                    .is_some_and(|name| name_patterns.is_match(name))
+                    || path_patterns.is_match(file)
+            }
+            // pytest's defaults
+            None => is_test_file(file),
+        };
+        if !matches_python_files {
+            return false;
+        }
+
+        // the closest nested configuration setting testpaths wins
+        let testpaths = self
+            .nested_testpaths
+            .iter()
+            .filter(|(dir, _)| file.starts_with(dir))
+            .max_by_key(|(dir, _)| dir.components().count())
+            .map_or(&self.testpaths, |(_, testpaths)| testpaths);
+        if !testpaths.is_empty() && !testpaths.iter().any(|p| file.starts_with(p)) {
+            return false;
+        }
+
+        match file.strip_prefix(&self.root_dir) {
+            Ok(relative) => !relative
+                .parent()
+                .into_iter()
+                .flat_map(Path::components)
+                .any(|dir| self.norecursedirs.is_match(dir.as_os_str())),
+            Err(_) => true,
+        }
+    }
+}
+
+// pytest uses `fnmatch`, in which wildcards also match path separators and braces
+// have no special meaning
+fn build_fnmatch_set<I, S>(patterns: I) -> Result<GlobSet>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let pattern = pattern.as_ref().replace('{', "[{]").replace('}', "[}]");
+        builder.add(Glob::new(&pattern)?);
+    }
+    Ok(builder.build()?)
+}
@@ -0,0 +1,139 @@
+use anyhow::Result;
+use rustc_hash::FxHashSet;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::path::Path;
+
+use crate::analysis::Analysis;
+use crate::explain;
+use crate::export::ExportedGraph;
+use crate::graph::{self, ImpactedNodes};
+use crate::pytest::TestFileMatcher;
+use crate::results::SnobResult;
+use crate::utils::{relative_to, LookupPaths};
+
+const TEMPLATE: &str = include_str!("report.html");
+const DATA_PLACEHOLDER: &str = "__SNOB_REPORT__";
+
+/// The data of the HTML report (`snob report`), embedded in the page. Paths are relative to the
+/// project root.
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub summary: ReportSummary,
+    /// the impacted files and the imports between them
+    pub graph: ExportedGraph,
+    /// impacted file -> the number of imports separating it from the closest changed file
+    pub depths: BTreeMap<String, usize>,
+    /// the tests to run
+    pub selected: BTreeSet<String>,
+    /// impacted test -> the shortest chain of imports from each changed file it depends on
+    pub paths: BTreeMap<String, Vec<Vec<ReportStep>>>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ReportSummary {
+    /// whether a changed file triggers all tests
+    pub run_all: bool,
+    pub changed: usize,
+    pub impacted_modules: usize,
+    pub impacted_tests: usize,
+    pub selected_tests: usize,
+    pub ignored_tests: usize,
+    pub total_tests: usize,
+}
+
+/// A file of an import chain (see `explain::ImportStep`)
+#[derive(Debug, Serialize)]
+pub struct ReportStep {
+    pub file: String,
+    /// the line of the statement importing the previous file of the chain
+    pub line: Option<usize>,
+    pub kind: Option<String>,
+}
+
+impl Report {
+    pub fn new(
+        analysis: &Analysis,
+        impacted_nodes: &ImpactedNodes,
+        changed: &FxHashSet<String>,
+        snob_results: &SnobResult,
+        test_matcher: &TestFileMatcher,
+        lookup_paths: &LookupPaths,
+        git_root: &Path,
+    ) -> Self {
+        let graph = ExportedGraph::new(
+            analysis,
+            impacted_nodes.nodes(),
+            changed,
+            test_matcher,
+            lookup_paths,
+            git_root,
+        );
+        let depths = impacted_nodes
+            .nodes()
+            .filter_map(|file| {
+                Some((
+                    relative_to(file, git_root),
+                    impacted_nodes.chain(file)?.len() - 1,
+                ))
+            })
+            .collect();
+        // reversed once for all the impacted tests
+        let dependencies_of = graph::reverse_graph(&analysis.dependency_graph);
+        let paths = impacted_nodes
+            .nodes()
+            .filter(|file| test_matcher.is_test_file(file))
+            .map(|test| {
+                let chains = explain::why_with(analysis, &dependencies_of, test, changed)
+                    .into_iter()
+                    .map(|chain| {
+                        chain
+                            .into_iter()
+                            .map(|step| ReportStep {
+                                file: relative_to(&step.file, git_root),
+                                line: step.site.map(|site| site.line),
+                                kind: step.site.map(|site| site.kind.to_string()),
+                            })
+                            .collect()
+                    })
+                    .collect();
+                (relative_to(test, git_root), chains)
+            })
+            .collect();
+
+        let summary = ReportSummary {
+            run_all: false,
+            changed: changed.len(),
+            impacted_modules: graph.nodes.values().filter(|node| !node.is_test).count(),
+            impacted_tests: graph.nodes.values().filter(|node| node.is_test).count(),
+            selected_tests: snob_results.selected().len(),
+            ignored_tests: snob_results.ignored.len(),
+            total_tests: analysis
+                .workspace_files
+                .iter()
+                .filter(|f| test_matcher.is_test_file(f))
+                .count(),
+        };
+
+        Self {
+            summary,
+            graph,
+            depths,
+            selected: snob_results
+                .selected()
+                .iter()
+                .map(|test| relative_to(test, git_root))
+                .collect(),
+            paths,
+        }
+    }
+
+    /// Write a self-contained HTML page (no network resource) to browse the report
+    pub fn write_html(&self, writer: &mut impl Write) -> Result<()> {
+        // `<` only appears within JSON strings, escaping it keeps `</script>` out of the data
+        let data = serde_json::to_string(self)?.replace('<', "\\u003c");
+        write!(writer, "{}", TEMPLATE.replacen(DATA_PLACEHOLDER, &data, 1))?;
+        Ok(())
+    }
+}
@@ -1,8 +1,11 @@
-use crate::utils::is_test_file;
+use crate::graph::ImpactedNodes;
+use crate::pytest::TestFileMatcher;
+use crate::utils::relative_to;
 use globset::GlobSet;
 use rustc_hash::FxHashSet;
-use std::collections::HashSet;
-use std::path::{Path, PathBuf};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::Path;
 
 #[derive(Debug, PartialEq)]
 pub struct SnobResult {
@@ -20,31 +23,27 @@ impl SnobResult {
         workspace_files: FxHashSet<String>,
         ignore_glob: &GlobSet,
         always_run_glob: &GlobSet,
+        test_matcher: &TestFileMatcher,
         git_root: &Path,
     ) -> Self {
+        // always run tests are selected by their path alone, whatever test discovery says
+        // (e.g. `tests/health_check.py` doesn't match pytest's default `python_files`)
         let always_run_tests = workspace_files
             .into_iter()
-            .filter(|f| {
-                !always_run_glob
-                    .matches(PathBuf::from(f).strip_prefix(git_root).unwrap())
-                    .is_empty()
-                    && is_test_file(f)
-            })
+            .filter(|f| !always_run_glob.matches(relative_to(f, git_root)).is_empty())
             .collect::<HashSet<String>>();
 
         let impacted_tests = impacted
             .into_iter()
-            .filter(|f| is_test_file(f))
+            .filter(|f| test_matcher.is_test_file(f))
             .collect::<HashSet<String>>();
 
+        // tests to always run have a higher priority than ignores
         let ignored_tests = impacted_tests
             .iter()
-            .map(std::string::ToString::to_string)
-            .filter(|f| {
-                !ignore_glob
-                    .matches(PathBuf::from(f).strip_prefix(git_root).unwrap())
-                    .is_empty()
-            })
+            .filter(|f| !always_run_tests.contains(*f))
+            .filter(|f| !ignore_glob.matches(relative_to(f, git_root)).is_empty())
+            .cloned()
             .collect::<HashSet<String>>();
         Self {
             impacted: impacted_tests.difference(&ignored_tests).cloned().collect(),
@@ -52,4 +51,99 @@ impl SnobResult {
             ignored: ignored_tests,
         }
     }
+
+    /// The tests to run: the impacted ones that are not ignored, along with the ones to always run
+    /// (whether they are ignored or not)
+    pub fn selected(&self) -> BTreeSet<String> {
+        self.impacted.union(&self.always_run).cloned().collect()
+    }
+}
+
+/// The impacted python files, tests or not (`--emit modules`), e.g. to scope type checkers and
+/// linters to the code affected by the changes
+///
+/// # Arguments
+/// * `exclude_tests` - When provided, the files it recognizes as tests are left out
+/// * `glob` - When provided, only the files matching it (relative to `git_root`) are kept
+pub fn impacted_modules(
+    impacted: &FxHashSet<String>,
+    project_files: &FxHashSet<String>,
+    exclude_tests: Option<&TestFileMatcher>,
+    glob: Option<&GlobSet>,
+    git_root: &Path,
+) -> BTreeSet<String> {
+    impacted
+        .iter()
+        // changed files that are not python files are not modules
+        .filter(|f| project_files.contains(*f))
+        .filter(|f| exclude_tests.is_none_or(|matcher| !matcher.is_test_file(f)))
+        .filter(|f| glob.is_none_or(|glob| glob.is_match(relative_to(f, git_root))))
+        .cloned()
+        .collect()
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SelectionMode {
+    /// all tests should run
+    All,
+    /// some tests should run
+    Partial,
+    /// no test should run
+    None,
+}
+
+/// The machine readable outcome of a selection (`--format json`)
+#[derive(Debug, Serialize)]
+pub struct SelectionReport {
+    pub mode: SelectionMode,
+    pub changed: BTreeSet<String>,
+    /// the tests to run (see `SnobResult::selected`)
+    pub selected: BTreeSet<String>,
+    pub impacted: BTreeSet<String>,
+    pub always_run: BTreeSet<String>,
+    pub ignored: BTreeSet<String>,
+    /// impacted test -> the files leading from a changed file to the test
+    pub reasons: BTreeMap<String, Vec<String>>,
+}
+
+impl SelectionReport {
+    /// A changed file triggers all tests
+    pub fn all(changed: &FxHashSet<String>) -> Self {
+        Self {
+            mode: SelectionMode::All,
+            changed: changed.iter().cloned().collect(),
+            selected: BTreeSet::new(),
+            impacted: BTreeSet::new(),
+            always_run: BTreeSet::new(),
+            ignored: BTreeSet::new(),
+            reasons: BTreeMap::new(),
+        }
+    }
+
+    pub fn new(
+        changed: &FxHashSet<String>,
+        result: &SnobResult,
+        impacted_nodes: &ImpactedNodes,
+    ) -> Self {
+        let selected = result.selected();
+        let mode = if selected.is_empty() {
+            SelectionMode::None
+        } else {
+            SelectionMode::Partial
+        };
+        Self {
+            mode,
+            changed: changed.iter().cloned().collect(),
+            selected,
+            impacted: result.impacted.iter().cloned().collect(),
+            always_run: result.always_run.iter().cloned().collect(),
+            ignored: result.ignored.iter().cloned().collect(),
+            reasons: result
+                .impacted
+                .iter()
+                .filter_map(|test| Some((test.clone(), impacted_nodes.chain(test)?)))
+                .collect(),
+        }
+    }
 }
@@ -0,0 +1,82 @@
+use anyhow::Result;
+use rustc_hash::FxHashSet;
+use std::path::Path;
+
+use crate::analysis::{Analysis, Layout};
+use crate::config::Config;
+use crate::graph::{self, ImpactedNodes};
+use crate::pytest::TestFileMatcher;
+use crate::results::SnobResult;
+use crate::source::SourceProvider;
+use crate::{fs, snob_debug};
+
+/// The tests selected for a set of changes, along with what they were selected from
+pub struct Selection {
+    pub analysis: Analysis,
+    /// the files impacted by the changes, each with the file it was reached from
+    pub predecessors: ImpactedNodes,
+    /// the impacted files that exist in the analyzed sources (all of them when `run_all`)
+    pub impacted_nodes: FxHashSet<String>,
+    pub test_matcher: TestFileMatcher,
+    pub results: SnobResult,
+}
+
+/// Analyze the python files provided by `source` (optionally along with the ones of
+/// `base_source`, see `graph::build_dependency_graph_from_source`) and select the tests impacted
+/// by `updated_files`, or every test when `run_all`.
+///
+/// This is the selection of the `snob` command, `snob report` and the python interface.
+#[allow(clippy::too_many_arguments)]
+pub fn select(
+    config: &Config,
+    layout: &Layout,
+    git_root: &Path,
+    source: &dyn SourceProvider,
+    base_source: Option<&dyn SourceProvider>,
+    // absolute paths
+    updated_files: &FxHashSet<String>,
+    deleted_files: &FxHashSet<String>,
+    run_all: bool,
+) -> Result<Selection> {
+    let analysis = Analysis::new(source, layout, config, git_root, deleted_files, base_source)?;
+
+    let predecessors =
+        graph::discover_impacted_nodes_with_predecessors(&analysis.dependency_graph, updated_files);
+    // every file is impacted when all tests run
+    let mut impacted_nodes = if run_all {
+        analysis.project_files.clone()
+    } else {
+        predecessors.nodes().cloned().collect::<FxHashSet<String>>()
+    };
+    // deleted files, as well as the ones only found in the base graph, can't be run anymore
+    impacted_nodes.retain(|f| source.exists(Path::new(f)));
+
+    // filter impacted nodes to get the tests
+    // just like `pytest` we consider files matching `python_files` (test_*.py or *_test.py by
+    // default) that live in `testpaths` and outside of `norecursedirs`
+    // see https://docs.pytest.org/en/stable/explanation/goodpractices.html#conventions-for-python-test-discovery
+    let ignored_tests = fs::build_glob_set(&config.tests.ignores)?;
+    let tests_to_always_run = fs::build_glob_set(&config.tests.always_run)?;
+    let test_matcher =
+        TestFileMatcher::new(config, git_root)?.with_test_targets(analysis.test_targets.clone());
+
+    let results = SnobResult::new(
+        impacted_nodes.clone(),
+        analysis.project_files.clone(),
+        &ignored_tests,
+        &tests_to_always_run,
+        &test_matcher,
+        git_root,
+    );
+    snob_debug!(" impacted tests: {:?}", results.impacted);
+    snob_debug!(" ignored tests: {:?}", results.ignored);
+    snob_debug!(" always run tests: {:?}", results.always_run);
+
+    Ok(Selection {
+        analysis,
+        predecessors,
+        impacted_nodes,
+        test_matcher,
+        results,
+    })
+}
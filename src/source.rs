@@ -0,0 +1,226 @@
+use anyhow::Result;
+use rustc_hash::{FxHashMap, FxHashSet, FxHasher};
+use std::hash::{Hash, Hasher};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::ast::INIT_FILE;
+use crate::fs;
+use crate::git::run_git;
+use crate::snob_debug;
+use crate::utils::LookupPaths;
+
+/// Where the python sources making up the dependency graph are read from
+pub trait SourceProvider: Sync {
+    /// List the python files found under any of the roots
+    fn list_files(&self, roots: &[PathBuf]) -> Vec<PathBuf>;
+
+    /// List the modules and packages directly importable from the lookup paths
+    fn first_level_components(&self, lookup_paths: &LookupPaths) -> Vec<PathBuf>;
+
+    fn read_to_string(&self, file: &Path) -> std::io::Result<String>;
+
+    fn exists(&self, file: &Path) -> bool;
+}
+
+/// The files currently on disk
+#[derive(Debug, Default)]
+pub struct WorkingTree;
+
+impl SourceProvider for WorkingTree {
+    fn list_files(&self, roots: &[PathBuf]) -> Vec<PathBuf> {
+        fs::crawl_workspace(roots)
+    }
+
+    fn first_level_components(&self, lookup_paths: &LookupPaths) -> Vec<PathBuf> {
+        fs::get_first_level_components(lookup_paths)
+    }
+
+    fn read_to_string(&self, file: &Path) -> std::io::Result<String> {
+        std::fs::read_to_string(file)
+    }
+
+    fn exists(&self, file: &Path) -> bool {
+        file.exists()
+    }
+}
+
+/// The python files of a git revision, read through git without checking the revision out
+#[derive(Debug)]
+pub struct GitRevision {
+    /// absolute path -> contents
+    files: FxHashMap<PathBuf, String>,
+    /// identifies the python files of the revision (paths and contents)
+    key: u64,
+}
+
+const PYTHON_EXTENSIONS: [&str; 2] = ["py", "pyi"];
+
+impl GitRevision {
+    pub fn new(repo_root: &Path, revision: &str) -> Result<Self> {
+        Self::with_blob_cache(repo_root, revision, &mut FxHashMap::default())
+    }
+
+    /// Same as `new`, only reading the blobs missing from `blob_cache` (object -> contents)
+    pub fn with_blob_cache(
+        repo_root: &Path,
+        revision: &str,
+        blob_cache: &mut FxHashMap<String, String>,
+    ) -> Result<Self> {
+        // <mode> SP <type> SP <object> TAB <path>
+        let tree = run_git(repo_root, &["ls-tree", "-r", "-z", revision])?;
+        let blobs = tree
+            .split('\0')
+            .filter_map(|entry| {
+                let (info, path) = entry.split_once('\t')?;
+                let mut info = info.split(' ');
+                let (_mode, kind, object) = (info.next()?, info.next()?, info.next()?);
+                let path = Path::new(path);
+                (kind == "blob"
+                    && path
+                        .extension()
+                        .is_some_and(|ext| PYTHON_EXTENSIONS.iter().any(|e| ext == *e)))
+                .then(|| (object.to_string(), repo_root.join(path)))
+            })
+            .collect::<Vec<_>>();
+        snob_debug!("Found {} python files at {}", blobs.len(), revision);
+
+        let mut hasher = FxHasher::default();
+        blobs.hash(&mut hasher);
+
+        let missing = blobs
+            .iter()
+            .map(|(object, _)| object.as_str())
+            .filter(|object| !blob_cache.contains_key(*object))
+            .collect::<FxHashSet<_>>();
+        let contents = read_blobs(repo_root, missing.iter().copied())?;
+        blob_cache.extend(missing.into_iter().map(ToString::to_string).zip(contents));
+
+        Ok(Self {
+            files: blobs
+                .into_iter()
+                .map(|(object, path)| (path, blob_cache[&object].clone()))
+                .collect(),
+            key: hasher.finish(),
+        })
+    }
+
+    /// Revisions with the same key have the same python files, and hence the same graph
+    pub fn key(&self) -> u64 {
+        self.key
+    }
+}
+
+impl SourceProvider for GitRevision {
+    // mirrors the crawl of the working tree, which skips hidden files and directories
+    fn list_files(&self, roots: &[PathBuf]) -> Vec<PathBuf> {
+        self.files
+            .keys()
+            .filter(|file| {
+                roots.iter().any(|root| {
+                    file.strip_prefix(root).is_ok_and(|relative| {
+                        !relative.components().any(|c| match c {
+                            Component::Normal(name) => name.to_string_lossy().starts_with('.'),
+                            _ => false,
+                        })
+                    })
+                })
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn first_level_components(&self, lookup_paths: &LookupPaths) -> Vec<PathBuf> {
+        let mut components = lookup_paths
+            .local_paths
+            .iter()
+            .flat_map(|lookup_path| {
+                self.files.keys().filter_map(move |file| {
+                    let relative = file.strip_prefix(lookup_path).ok()?;
+                    let mut relative_components = relative.components();
+                    match (relative_components.next(), relative_components.next()) {
+                        // module
+                        (Some(_), None) if file.extension().is_some_and(|ext| ext == "py") => {
+                            Some(file.clone())
+                        }
+                        // package
+                        (Some(package), Some(init)) if relative_components.next().is_none() => {
+                            (init.as_os_str() == INIT_FILE)
+                                .then(|| lookup_path.join(package.as_os_str()))
+                        }
+                        _ => None,
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        // keep lookup paths order, and a stable order within each of them
+        components.sort_by_key(|c| {
+            (
+                lookup_paths
+                    .local_paths
+                    .iter()
+                    .position(|p| c.starts_with(p)),
+                c.clone(),
+            )
+        });
+        components
+    }
+
+    fn read_to_string(&self, file: &Path) -> std::io::Result<String> {
+        self.files.get(file).cloned().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("File {file:?} does not exist at this revision"),
+            )
+        })
+    }
+
+    fn exists(&self, file: &Path) -> bool {
+        self.files.contains_key(file)
+    }
+}
+
+// reads all blobs with a single `git cat-file --batch` process
+fn read_blobs<'a, I>(repo_root: &Path, objects: I) -> Result<Vec<String>>
+where
+    I: Iterator<Item = &'a str>,
+{
+    let input = objects.map(|o| format!("{o}\n")).collect::<String>();
+    let mut child = Command::new("git")
+        .args(["cat-file", "--batch"])
+        .current_dir(repo_root)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    // write from another thread so that a full stdout pipe can't block us
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+
+    let mut stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+    let mut blobs = Vec::new();
+    let mut header = String::new();
+    // <object> SP <type> SP <size> LF <contents> LF
+    while stdout.read_line(&mut header)? > 0 {
+        let size = header
+            .trim_end()
+            .rsplit(' ')
+            .next()
+            .and_then(|s| s.parse::<usize>().ok())
+            .ok_or_else(|| anyhow::anyhow!("Unexpected `git cat-file` output: {header}"))?;
+        let mut contents = vec![0; size + 1];
+        stdout.read_exact(&mut contents)?;
+        contents.pop();
+        blobs.push(String::from_utf8_lossy(&contents).to_string());
+        header.clear();
+    }
+
+    writer
+        .join()
+        .map_err(|_| anyhow::anyhow!("Failed to write to `git cat-file`"))??;
+    if !child.wait()?.success() {
+        anyhow::bail!("`git cat-file --batch` failed");
+    }
+    Ok(blobs)
+}
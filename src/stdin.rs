@@ -1,26 +1,50 @@
 #[allow(unused_imports)]
 use log::debug;
-use std::io::{stdin, BufRead};
+use std::io::{stdin, Read};
 
-pub fn read_from_stdin() -> Vec<String> {
-    let mut stdin = stdin().lock();
-    let mut buffer = String::new();
+use crate::git::{parse_name_status_fields, FileChange};
 
-    let mut lines = Vec::new();
+/// How the changed files read from stdin are formatted
+#[derive(Debug, Default, Clone, Copy)]
+pub struct InputFormat {
+    /// entries are separated by NUL characters instead of newlines, and are not trimmed
+    pub nul_separated: bool,
+    /// entries come from `git diff --name-status` (a status followed by one or two paths)
+    pub name_status: bool,
+}
 
-    loop {
-        match stdin.read_line(&mut buffer) {
-            Ok(c) if c > 0 => {
-                if !buffer.trim().is_empty() {
-                    lines.push(buffer.trim().to_string());
-                }
-                buffer.clear();
-            }
-            _ => break,
-        }
-    }
+pub fn read_from_stdin(format: InputFormat) -> std::io::Result<Vec<FileChange>> {
+    let mut input = String::new();
+    stdin().lock().read_to_string(&mut input)?;
+    Ok(parse_input(&input, format))
+}
+
+pub fn parse_input(input: &str, format: InputFormat) -> Vec<FileChange> {
+    let entries: Vec<&str> = if format.nul_separated {
+        input.split('\0').filter(|e| !e.is_empty()).collect()
+    } else {
+        input
+            .lines()
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+            .collect()
+    };
 
-    lines
+    if !format.name_status {
+        entries
+            .into_iter()
+            .map(|path| FileChange {
+                path: path.to_string(),
+                deleted: false,
+            })
+            .collect()
+    } else if format.nul_separated {
+        // statuses and paths are separate entries
+        parse_name_status_fields(entries)
+    } else {
+        // <status> TAB <path> [TAB <path>]
+        parse_name_status_fields(entries.into_iter().flat_map(|line| line.split('\t')))
+    }
 }
 
 /// Heuristic to determine if stdin is readable.
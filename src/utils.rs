@@ -1,10 +1,11 @@
+use crate::ast::ImportEdge;
 use globset::GlobSet;
 #[cfg(feature = "python")]
 use pyo3::{exceptions::PyFileNotFoundError, PyErr};
 use rustc_hash::{FxHashMap, FxHashSet};
 use std::{
     fmt::Display,
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
 };
 
 pub fn merge_hashmaps<K, V, A>(hashmaps: &mut [FxHashMap<K, V>]) -> FxHashMap<K, V>
@@ -74,7 +75,7 @@ impl Default for LookupPaths {
 }
 
 // python's import paths: [cwd, PYTHONPATH, others]
-pub fn get_python_local_lookup_paths(current_dir: &Path, git_root: &PathBuf) -> LookupPaths {
+pub fn get_python_local_lookup_paths(current_dir: &Path, git_root: &Path) -> LookupPaths {
     // ordered
     let pythonpath = get_pythonpath();
     let mut local_paths = vec![current_dir.to_path_buf()];
@@ -105,11 +106,17 @@ pub fn get_pythonpath() -> Vec<PathBuf> {
 }
 
 #[derive(Debug)]
-pub struct GitRootError;
+pub struct GitRootError {
+    markers: Vec<String>,
+}
 
 impl Display for GitRootError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Could not find git root")
+        write!(
+            f,
+            "Could not find the project root (looked for {}), use `--root` to set it explicitly",
+            self.markers.join(", ")
+        )
     }
 }
 
@@ -122,17 +129,92 @@ impl std::convert::From<GitRootError> for PyErr {
     }
 }
 
+const GIT_DIR: &str = ".git";
+
+/// Files and directories marking the root of the project, by order of precedence
+pub const DEFAULT_ROOT_MARKERS: [&str; 5] = [GIT_DIR, ".hg", ".jj", "snob.toml", "pyproject.toml"];
+
+/// Comma separated root markers replacing `DEFAULT_ROOT_MARKERS`
+const SNOB_ROOT_MARKERS_ENV: &str = "SNOB_ROOT_MARKERS";
+
+/// Find the project root with the root markers of `SNOB_ROOT_MARKERS`, or the default ones
 pub fn get_repo_root(current_dir: &Path) -> Result<PathBuf, GitRootError> {
-    let mut path = current_dir.to_path_buf();
-    // let's cross our fingers here
-    while !path.join(".git").exists() {
-        if let Some(p) = path.parent() {
-            path = p.to_path_buf();
-        } else {
-            return Err(GitRootError);
+    let markers = match std::env::var(SNOB_ROOT_MARKERS_ENV) {
+        Ok(markers) => markers
+            .split(',')
+            .map(str::trim)
+            .filter(|m| !m.is_empty())
+            .map(ToString::to_string)
+            .collect(),
+        Err(_) => DEFAULT_ROOT_MARKERS.map(ToString::to_string).to_vec(),
+    };
+    find_project_root(current_dir, &markers)
+}
+
+/// Find the closest ancestor of `current_dir` containing the first of `markers` (by order of
+/// precedence) that can be found.
+///
+/// A submodule is analyzed as part of its superproject: when the closest `.git` is a submodule's,
+/// the root of the outermost superproject is returned.
+pub fn find_project_root(current_dir: &Path, markers: &[String]) -> Result<PathBuf, GitRootError> {
+    markers
+        .iter()
+        .find_map(|marker| {
+            let root = current_dir
+                .ancestors()
+                .find(|dir| dir.join(marker).exists())?;
+            if marker == GIT_DIR {
+                Some(get_superproject_root(root).unwrap_or_else(|| root.to_path_buf()))
+            } else {
+                Some(root.to_path_buf())
+            }
+        })
+        .ok_or_else(|| GitRootError {
+            markers: markers.to_vec(),
+        })
+}
+
+/// `path` relative to `root` (e.g. the project root) when it lives below it, `path` itself
+/// otherwise
+pub fn relative_to(path: impl AsRef<Path>, root: &Path) -> String {
+    let path = path.as_ref();
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Resolve the `.` and `..` components of a path lexically, without following symlinks
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            component => normalized.push(component),
         }
     }
-    Ok(path)
+    normalized
+}
+
+// a submodule's `.git` is a file pointing to `<superproject>/.git/modules/<name>`, whereas
+// worktrees point to `<repository>/.git/worktrees/<name>` and are roots of their own
+fn get_superproject_root(root: &Path) -> Option<PathBuf> {
+    let git_file = std::fs::read_to_string(root.join(GIT_DIR)).ok()?;
+    // the path is usually relative (`../.git/modules/<name>`), resolve it without following
+    // symlinks so that the superproject root stays comparable with `current_dir`
+    let git_dir = normalize_path(&root.join(git_file.trim().strip_prefix("gitdir:")?.trim()));
+    // nested submodules live in `.git/modules/<name>/modules/<name>`, take the outermost one
+    let mut ancestors = git_dir.ancestors().collect::<Vec<_>>();
+    ancestors.reverse();
+    ancestors.windows(2).find_map(|pair| {
+        let (dir, child) = (pair[0], pair[1]);
+        (dir.file_name().is_some_and(|n| n == GIT_DIR) && child.ends_with("modules"))
+            .then(|| dir.parent().map(Path::to_path_buf))
+            .flatten()
+    })
 }
 
 #[cfg(test)]
@@ -255,13 +337,13 @@ pub fn should_run_all_tests(
 ) -> bool {
     updated_files.iter().any(|f| {
         !run_all_tests_on_change
-            .matches(PathBuf::from(f).strip_prefix(git_root).unwrap())
+            .matches(relative_to(f, git_root))
             .is_empty()
     })
 }
 
 pub fn deduplicate_dependencies(
-    dependencies: FxHashMap<String, Vec<String>>,
+    dependencies: &FxHashMap<String, Vec<ImportEdge>>,
 ) -> FxHashMap<String, FxHashSet<String>> {
     dependencies
         .iter()
@@ -269,7 +351,7 @@ pub fn deduplicate_dependencies(
             (
                 k.to_string(),
                 v.iter()
-                    .map(std::string::ToString::to_string)
+                    .map(|edge| edge.importer.clone())
                     .collect::<FxHashSet<_>>(),
             )
         })
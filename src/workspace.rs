@@ -0,0 +1,220 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use ignore::WalkBuilder;
+use rustc_hash::FxHashSet;
+
+use crate::fs::build_glob_set;
+use crate::snob_debug;
+use crate::utils::{self, LookupPaths};
+
+const PYPROJECT_FILE: &str = "pyproject.toml";
+// see https://packaging.python.org/en/latest/discussions/src-layout-vs-flat-layout/
+const SRC_LAYOUT_DIR: &str = "src";
+
+/// A project of a uv / Poetry workspace
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkspaceMember {
+    /// the project name as declared in its `pyproject.toml` (or its directory name)
+    pub name: String,
+    /// the directory containing the member's `pyproject.toml`
+    pub root: PathBuf,
+    /// the directory python imports the member's packages from
+    pub import_root: PathBuf,
+}
+
+impl WorkspaceMember {
+    fn new(root: PathBuf) -> Self {
+        let name = read_pyproject(&root)
+            .and_then(|pyproject| {
+                pyproject
+                    .get("project")
+                    .or_else(|| pyproject.get("tool").and_then(|t| t.get("poetry")))
+                    .and_then(|p| p.get("name"))
+                    .and_then(|n| n.as_str())
+                    .map(std::string::ToString::to_string)
+            })
+            .or_else(|| root.file_name().map(|n| n.to_string_lossy().to_string()))
+            .unwrap_or_default();
+        let import_root = if root.join(SRC_LAYOUT_DIR).is_dir() {
+            root.join(SRC_LAYOUT_DIR)
+        } else {
+            root.clone()
+        };
+        Self {
+            name,
+            root,
+            import_root,
+        }
+    }
+}
+
+fn read_pyproject(dir: &Path) -> Option<toml::Value> {
+    std::fs::read_to_string(dir.join(PYPROJECT_FILE))
+        .ok()
+        .and_then(|contents| toml::from_str(&contents).ok())
+}
+
+fn as_string_set(value: Option<&toml::Value>) -> FxHashSet<String> {
+    value
+        .and_then(|v| v.as_array())
+        .map(|a| {
+            a.iter()
+                .filter_map(|v| v.as_str())
+                .map(std::string::ToString::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Discover the members of the workspace defined at the root of the repository.
+///
+/// Members are read from `[tool.uv.workspace]` (`members` / `exclude` globs) and from Poetry
+/// path dependencies (`{ path = "..." }` entries of `[tool.poetry.dependencies]` and of its
+/// dependency groups). The root project is itself a member when it declares one.
+/// Returns an empty list for single-project repositories.
+pub fn discover_workspace_members(repo_root: &Path) -> Result<Vec<WorkspaceMember>> {
+    let Some(pyproject) = read_pyproject(repo_root) else {
+        return Ok(Vec::new());
+    };
+    let tool = pyproject.get("tool");
+
+    let mut member_roots: Vec<PathBuf> = Vec::new();
+
+    if let Some(uv_workspace) = tool
+        .and_then(|t| t.get("uv"))
+        .and_then(|uv| uv.get("workspace"))
+    {
+        let members = as_string_set(uv_workspace.get("members"));
+        let exclude = as_string_set(uv_workspace.get("exclude"));
+        member_roots.extend(expand_member_globs(repo_root, &members, &exclude)?);
+    }
+
+    if let Some(poetry) = tool.and_then(|t| t.get("poetry")) {
+        let groups = poetry
+            .get("group")
+            .and_then(|g| g.as_table())
+            .into_iter()
+            .flat_map(|groups| groups.values())
+            .filter_map(|g| g.get("dependencies"));
+        let path_dependencies = poetry
+            .get("dependencies")
+            .into_iter()
+            .chain(groups)
+            .filter_map(|deps| deps.as_table())
+            .flat_map(|deps| deps.values())
+            .filter_map(|dep| dep.get("path").and_then(|p| p.as_str()))
+            .map(|p| repo_root.join(p))
+            .filter(|p| p.join(PYPROJECT_FILE).is_file());
+        member_roots.extend(path_dependencies);
+    }
+
+    if member_roots.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // the workspace root is a member as well when it defines a project
+    if pyproject.get("project").is_some() || tool.and_then(|t| t.get("poetry")).is_some() {
+        member_roots.push(repo_root.to_path_buf());
+    }
+
+    let mut seen = FxHashSet::default();
+    let members = member_roots
+        .into_iter()
+        // path dependencies may go through `..` (e.g. `../lib`), whereas member roots must
+        // prefix-match the paths of their files
+        .map(|p| utils::normalize_path(&std::path::absolute(&p).unwrap_or(p)))
+        // the analyzed files must live in the repository
+        .filter(|p| {
+            let inside = p.starts_with(repo_root);
+            if !inside {
+                snob_debug!(
+                    "Ignoring workspace member {:?} outside of the repository",
+                    p
+                );
+            }
+            inside
+        })
+        .filter(|p| seen.insert(p.clone()))
+        .map(WorkspaceMember::new)
+        .collect::<Vec<_>>();
+    for member in &members {
+        snob_debug!("Workspace member {:?} at {:?}", member.name, member.root);
+    }
+    Ok(members)
+}
+
+// uv member globs are relative to the workspace root and point at directories
+// containing a `pyproject.toml`
+fn expand_member_globs(
+    repo_root: &Path,
+    members: &FxHashSet<String>,
+    exclude: &FxHashSet<String>,
+) -> Result<Vec<PathBuf>> {
+    if members.is_empty() {
+        return Ok(Vec::new());
+    }
+    let members_glob = build_glob_set(members)?;
+    let exclude_glob = build_glob_set(exclude)?;
+    let max_depth = if members.iter().any(|m| m.contains("**")) {
+        None
+    } else {
+        members
+            .iter()
+            .map(|m| Path::new(m).components().count())
+            .max()
+    };
+
+    Ok(WalkBuilder::new(repo_root)
+        .max_depth(max_depth)
+        .build()
+        .filter_map(std::result::Result::ok)
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_dir()))
+        .map(ignore::DirEntry::into_path)
+        .filter(|p| {
+            p.strip_prefix(repo_root).is_ok_and(|relative| {
+                members_glob.is_match(relative) && !exclude_glob.is_match(relative)
+            })
+        })
+        .filter(|p| p.join(PYPROJECT_FILE).is_file())
+        .collect())
+}
+
+/// Add the members' import roots to the lookup paths, after the ones python would use anyway
+pub fn extend_lookup_paths(lookup_paths: &mut LookupPaths, members: &[WorkspaceMember]) {
+    members
+        .iter()
+        .for_each(|m| lookup_paths.insert(m.import_root.clone()));
+}
+
+/// Group test files by the workspace member they belong to (the member with the deepest root
+/// containing them).
+/// Tests that do not belong to any member are grouped under the repository root.
+/// Test paths are returned relative to their group's directory so that they can be passed to a
+/// test runner invoked from there.
+pub fn group_by_member<'a, I>(
+    tests: I,
+    members: &[WorkspaceMember],
+    repo_root: &Path,
+) -> BTreeMap<PathBuf, Vec<PathBuf>>
+where
+    I: IntoIterator<Item = &'a String>,
+{
+    let mut groups: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+    for test in tests {
+        let test = Path::new(test);
+        let group_root = members
+            .iter()
+            .map(|m| m.root.as_path())
+            .filter(|root| test.starts_with(root))
+            .max_by_key(|root| root.components().count())
+            .unwrap_or(repo_root);
+        groups
+            .entry(group_root.to_path_buf())
+            .or_default()
+            .push(test.strip_prefix(group_root).unwrap_or(test).to_path_buf());
+    }
+    groups.values_mut().for_each(|tests| tests.sort());
+    groups
+}
@@ -0,0 +1,246 @@
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+use anyhow::Result;
+use tempfile::TempDir;
+
+mod test_utils;
+
+fn create_project() -> Result<TempDir> {
+    test_utils::create_project(&[
+        ("foo.py", "def foo():\n    pass\n"),
+        ("bar.py", "def bar():\n    pass\n"),
+        ("tests/test_foo.py", "from foo import foo\n"),
+        ("tests/test_bar.py", "from bar import bar\n"),
+    ])
+}
+
+// run snob from `current_dir`, feeding it `stdin` when given (stdin is not readable otherwise)
+fn snob(current_dir: &Path, args: &[&str], stdin: Option<&str>) -> Result<Output> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_snob"))
+        .args(args)
+        .current_dir(current_dir)
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(input) = stdin {
+        child.stdin.take().unwrap().write_all(input.as_bytes())?;
+    }
+    Ok(child.wait_with_output()?)
+}
+
+fn stdout_lines(output: &Output) -> Vec<String> {
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(ToString::to_string)
+        .collect()
+}
+
+fn assert_rejected(output: &Output, message: &str) {
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(message), "unexpected stderr: {stderr}");
+}
+
+#[test]
+fn test_dash_reads_changed_files_from_stdin() -> Result<()> {
+    let temp_dir = create_project()?;
+    let root = temp_dir.path();
+
+    let output = snob(root, &["-"], Some("foo.py\n"))?;
+    assert!(output.status.success());
+    assert_eq!(
+        stdout_lines(&output),
+        vec![root.join("tests/test_foo.py").display().to_string()]
+    );
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("deprecated"));
+
+    // `-` and `--stdin` add to the files given as arguments
+    let output = snob(root, &["bar.py", "--stdin"], Some("foo.py\n"))?;
+    assert!(output.status.success());
+    let mut selected = stdout_lines(&output);
+    selected.sort();
+    assert_eq!(
+        selected,
+        vec![
+            root.join("tests/test_bar.py").display().to_string(),
+            root.join("tests/test_foo.py").display().to_string(),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_implicit_stdin_is_deprecated() -> Result<()> {
+    let temp_dir = create_project()?;
+    let root = temp_dir.path();
+
+    let output = snob(root, &[], Some("foo.py\n"))?;
+    assert!(output.status.success());
+    assert_eq!(
+        stdout_lines(&output),
+        vec![root.join("tests/test_foo.py").display().to_string()]
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("deprecated"));
+
+    // changed files given as arguments leave stdin alone
+    let output = snob(root, &["bar.py"], Some("foo.py\n"))?;
+    assert!(output.status.success());
+    assert_eq!(
+        stdout_lines(&output),
+        vec![root.join("tests/test_bar.py").display().to_string()]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_name_status_from_stdin() -> Result<()> {
+    let temp_dir = create_project()?;
+    let root = temp_dir.path();
+
+    let output = snob(
+        root,
+        &["-z", "--name-status", "-"],
+        Some("M\0foo.py\0D\0gone.py\0"),
+    )?;
+    assert!(output.status.success());
+    assert_eq!(
+        stdout_lines(&output),
+        vec![root.join("tests/test_foo.py").display().to_string()]
+    );
+    // the deleted file was reported as such
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("gone.py"));
+    Ok(())
+}
+
+#[test]
+fn test_input_format_requires_stdin() -> Result<()> {
+    let temp_dir = create_project()?;
+    let root = temp_dir.path();
+
+    for args in [&["-z", "foo.py"][..], &["--name-status", "foo.py"]] {
+        assert_rejected(&snob(root, args, None)?, "requires `-` (or `--stdin`)");
+        // even when stdin could be read implicitly
+        assert_rejected(
+            &snob(root, &args[..1], Some("foo.py\n"))?,
+            "requires `-` (or `--stdin`)",
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_module_options_require_emit_modules() -> Result<()> {
+    let temp_dir = create_project()?;
+    let root = temp_dir.path();
+
+    for args in [
+        &["--exclude-tests", "foo.py"][..],
+        &["--modules-glob", "*.py", "foo.py"],
+    ] {
+        assert_rejected(&snob(root, args, None)?, "require `--emit modules`");
+    }
+    for args in [
+        &["--emit", "modules", "--format", "json", "foo.py"][..],
+        &["--emit", "modules", "--group-by-member", "foo.py"],
+    ] {
+        assert_rejected(&snob(root, args, None)?, "`--emit modules` prints one file");
+    }
+
+    let output = snob(
+        root,
+        &["--emit", "modules", "--exclude-tests", "foo.py"],
+        None,
+    )?;
+    assert!(output.status.success());
+    assert_eq!(
+        stdout_lines(&output),
+        vec![root.join("foo.py").display().to_string()]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_subcommands_reject_change_selection() -> Result<()> {
+    let temp_dir = create_project()?;
+    let root = temp_dir.path();
+
+    for args in [
+        &["--staged", "why", "tests/test_foo.py"][..],
+        &["--revision", "HEAD", "deps", "foo.py"],
+    ] {
+        assert_rejected(
+            &snob(root, args, None)?,
+            "only apply to the selection of tests",
+        );
+    }
+    // global flags before the subcommand are still accepted
+    let output = snob(root, &["-q", "why", "tests/test_foo.py", "foo.py"], None)?;
+    assert!(output.status.success());
+    Ok(())
+}
+
+#[test]
+fn test_root_with_parent_components() -> Result<()> {
+    let temp_dir = create_project()?;
+    let root = temp_dir.path();
+
+    let parent_root = format!("../{}", root.file_name().unwrap().to_string_lossy());
+    for root_arg in [parent_root.as_str(), "./tests/.."] {
+        let output = snob(root, &["--root", root_arg, "foo.py"], None)?;
+        assert!(output.status.success(), "{output:?}");
+        assert_eq!(
+            stdout_lines(&output),
+            vec![root.join("tests/test_foo.py").display().to_string()]
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_target_directory_with_parent_components() -> Result<()> {
+    let temp_dir = test_utils::create_project(&[
+        ("src/foo.py", "def foo():\n    pass\n"),
+        ("tests/test_foo.py", "from foo import foo\n"),
+    ])?;
+    let root = temp_dir.path();
+
+    // `src` is the lookup path snob runs from, the tests live next to it
+    for changed in ["foo.py", "../src/foo.py"] {
+        let output = snob(&root.join("src"), &["-t", "../tests", changed], None)?;
+        assert!(output.status.success(), "{output:?}");
+        assert_eq!(
+            stdout_lines(&output),
+            vec![root.join("tests/test_foo.py").display().to_string()]
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_report_selects_all_tests_when_a_change_triggers_them() -> Result<()> {
+    let temp_dir = create_project()?;
+    let root = temp_dir.path();
+    std::fs::write(
+        root.join("snob.toml"),
+        "[files]\nrun-all-tests-on-change = [\"conftest.py\"]\n",
+    )?;
+    std::fs::write(root.join("conftest.py"), "")?;
+
+    let output = snob(
+        root,
+        &["report", "--html", "report.html", "conftest.py"],
+        None,
+    )?;
+    assert!(output.status.success(), "{output:?}");
+    let html = std::fs::read_to_string(root.join("report.html"))?;
+    assert!(html.contains("\"run_all\":true"));
+    assert!(html.contains("\"selected\":[\"tests/test_bar.py\",\"tests/test_foo.py\"]"));
+    Ok(())
+}
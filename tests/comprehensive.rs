@@ -26,7 +26,7 @@ fn test_with_clean_imports_simple_project() -> Result<()> {
     .into_iter()
     .collect::<FxHashSet<String>>();
 
-    let config = Config::new(&project_path);
+    let config = Config::new(&project_path)?;
     let result = get_impacted_tests_from_changed_files(
         &config,
         &project_path,
@@ -67,7 +67,7 @@ fn test_with_clean_imports_complex_project() -> Result<()> {
     .into_iter()
     .collect::<FxHashSet<String>>();
 
-    let config = Config::new(&project_path);
+    let config = Config::new(&project_path)?;
     let result = get_impacted_tests_from_changed_files(
         &config,
         &project_path,
@@ -107,7 +107,7 @@ fn test_with_clean_imports_isolated_project() -> Result<()> {
     .into_iter()
     .collect::<FxHashSet<String>>();
 
-    let config = Config::new(&project_path);
+    let config = Config::new(&project_path)?;
     let result = get_impacted_tests_from_changed_files(
         &config,
         &project_path,
@@ -147,7 +147,7 @@ fn test_with_config_file() -> Result<()> {
     .into_iter()
     .collect::<FxHashSet<String>>();
 
-    let config = Config::new(&project_path);
+    let config = Config::new(&project_path)?;
     let result = get_impacted_tests_from_changed_files(
         &config,
         &project_path,
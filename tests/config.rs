@@ -0,0 +1,252 @@
+use anyhow::Result;
+use std::fs;
+use tempfile::TempDir;
+
+use snob_lib::config::Config;
+use snob_lib::pytest::TestFileMatcher;
+
+#[test]
+fn test_nested_config_globs_are_relative_to_their_directory() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+
+    fs::create_dir_all(root.join("services/payments"))?;
+    fs::write(
+        root.join("snob.toml"),
+        r#"
+[files]
+run-all-tests-on-change = ["conftest.py"]
+
+[tests]
+always-run = ["tests/test_health.py"]
+"#,
+    )?;
+    fs::write(
+        root.join("services/payments/snob.toml"),
+        r#"
+[files]
+ignores = ["migrations/**/*.py"]
+run-all-tests-on-change = ["conftest.py"]
+
+[tests]
+ignores = ["tests/test_slow.py"]
+"#,
+    )?;
+
+    let config = Config::new(root)?;
+
+    assert!(config
+        .files
+        .ignores
+        .contains("services/payments/migrations/**/*.py"));
+    assert!(config.files.run_all_tests_on_change.contains("conftest.py"));
+    assert!(config
+        .files
+        .run_all_tests_on_change
+        .contains("services/payments/conftest.py"));
+    assert!(config.tests.always_run.contains("tests/test_health.py"));
+    assert!(config
+        .tests
+        .ignores
+        .contains("services/payments/tests/test_slow.py"));
+
+    Ok(())
+}
+
+#[test]
+fn test_nested_pyproject_config() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+
+    fs::create_dir_all(root.join("packages/api"))?;
+    fs::create_dir_all(root.join("packages/cli"))?;
+    fs::write(
+        root.join("packages/api/pyproject.toml"),
+        r#"
+[project]
+name = "api"
+
+[tool.snob.tests]
+always-run = ["tests/test_smoke.py"]
+"#,
+    )?;
+    // no snob section: nothing to merge
+    fs::write(
+        root.join("packages/cli/pyproject.toml"),
+        "[project]\nname = \"cli\"\n",
+    )?;
+    // not valid TOML, nor snob's business: skipped
+    fs::create_dir_all(root.join("packages/legacy"))?;
+    fs::write(
+        root.join("packages/legacy/pyproject.toml"),
+        "[project\nname = \"legacy\"\n",
+    )?;
+
+    let config = Config::new(root)?;
+
+    assert_eq!(
+        config.tests.always_run.iter().collect::<Vec<_>>(),
+        vec!["packages/api/tests/test_smoke.py"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_nested_testpaths_only_apply_below_their_directory() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+
+    fs::create_dir_all(root.join("packages/api"))?;
+    fs::write(
+        root.join("pytest.ini"),
+        "[pytest]\ntestpaths = tests packages\n",
+    )?;
+    fs::write(
+        root.join("packages/api/snob.toml"),
+        "[tests]\ntestpaths = [\"checks\"]\n",
+    )?;
+
+    // the root configuration has no testpaths: pytest's ones still apply outside of the nested
+    // configuration's directory
+    let config = Config::new(root)?;
+    assert_eq!(config.tests.testpaths, None);
+
+    let matcher = TestFileMatcher::new(&config, root)?;
+    assert!(matcher.is_test_file(root.join("tests/test_app.py")));
+    assert!(matcher.is_test_file(root.join("packages/cli/test_cli.py")));
+    assert!(!matcher.is_test_file(root.join("scripts/test_script.py")));
+    assert!(matcher.is_test_file(root.join("packages/api/checks/test_api.py")));
+    assert!(!matcher.is_test_file(root.join("packages/api/test_api.py")));
+
+    Ok(())
+}
+
+#[test]
+fn test_explicit_config_file() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+
+    fs::create_dir_all(root.join("ci"))?;
+    fs::write(
+        root.join("snob.toml"),
+        "[tests]\nignores = [\"tests/test_local.py\"]\n",
+    )?;
+    fs::write(
+        root.join("ci/snob-ci.toml"),
+        "[tests]\nignores = [\"tests/test_ci.py\"]\n",
+    )?;
+
+    let config = Config::load(root, Some(&root.join("ci/snob-ci.toml")))?;
+
+    assert_eq!(
+        config.tests.ignores.iter().collect::<Vec<_>>(),
+        vec!["tests/test_ci.py"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_explicit_config_file_is_not_nested() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+
+    fs::create_dir_all(root.join("ci"))?;
+    fs::write(
+        root.join("ci/snob.toml"),
+        "[tests]\nignores = [\"tests/test_ci.py\"]\npython-files = [\"check_*.py\"]\n",
+    )?;
+
+    // root-only keys are accepted, and the globs stay relative to the repository root
+    let config = Config::load(root, Some(&root.join("ci/./snob.toml")))?;
+
+    assert_eq!(
+        config.tests.ignores.iter().collect::<Vec<_>>(),
+        vec!["tests/test_ci.py"]
+    );
+    assert_eq!(
+        config.tests.python_files,
+        Some(vec!["check_*.py".to_string()])
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_unknown_key_is_rejected() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+
+    fs::write(
+        root.join("snob.toml"),
+        "[files]\nignores = []\nrun_all_tests_on_change = [\"conftest.py\"]\n",
+    )?;
+
+    let error = Config::new(root).unwrap_err();
+
+    assert_eq!(error.path, root.join("snob.toml"));
+    assert_eq!(error.location, Some((3, 1)));
+    assert_eq!(error.key.as_deref(), Some("files.run_all_tests_on_change"));
+    assert!(error.message.contains("unknown field"));
+
+    Ok(())
+}
+
+#[test]
+fn test_invalid_value_in_pyproject() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+
+    fs::write(
+        root.join("pyproject.toml"),
+        "[project]\nname = \"app\"\n\n[tool.snob.tests]\nalways-run = \"tests/test_smoke.py\"\n",
+    )?;
+
+    let error = Config::new(root).unwrap_err();
+
+    assert_eq!(error.location, Some((5, 14)));
+    assert_eq!(error.key.as_deref(), Some("tool.snob.tests.always-run"));
+
+    Ok(())
+}
+
+#[test]
+fn test_invalid_glob_is_rejected() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+
+    fs::create_dir_all(root.join("services"))?;
+    fs::write(
+        root.join("services/snob.toml"),
+        "[tests]\n\nignores = [\n  \"tests/**/*.py\",\n  \"tests/[slow.py\",\n]\n",
+    )?;
+
+    let error = Config::new(root).unwrap_err();
+
+    assert_eq!(error.path, root.join("services/snob.toml"));
+    assert_eq!(error.location, Some((5, 4)));
+    assert_eq!(error.key.as_deref(), Some("tests.ignores"));
+
+    Ok(())
+}
+
+#[test]
+fn test_nested_python_files_is_rejected() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+
+    fs::create_dir_all(root.join("packages/api"))?;
+    fs::write(
+        root.join("packages/api/pyproject.toml"),
+        "[project]\nname = \"api\"\n\n[tool.snob.tests]\npython-files = [\"check_*.py\"]\n",
+    )?;
+
+    let error = Config::new(root).unwrap_err();
+
+    assert_eq!(error.path, root.join("packages/api/pyproject.toml"));
+    assert_eq!(error.location, Some((5, 1)));
+    assert_eq!(error.key.as_deref(), Some("tool.snob.tests.python-files"));
+
+    Ok(())
+}
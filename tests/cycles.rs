@@ -0,0 +1,74 @@
+use anyhow::Result;
+use std::fs;
+use tempfile::TempDir;
+
+use snob_lib::ast::{ImportKind, ImportSite};
+use snob_lib::cycles::{find_import_cycles, new_cycles, read_baseline, write_baseline};
+
+mod test_utils;
+use test_utils::analyze;
+
+fn create_project() -> Result<TempDir> {
+    test_utils::create_project(&[
+        ("pkg/__init__.py", ""),
+        ("pkg/a.py", "import os\nfrom pkg import b\n"),
+        ("pkg/b.py", "from . import a\n"),
+        ("pkg/c.py", "import pkg.a\n"),
+    ])
+}
+
+#[test]
+fn test_find_import_cycles() -> Result<()> {
+    let temp_dir = create_project()?;
+    let root = temp_dir.path();
+    let path = |f: &str| root.join(f).to_string_lossy().to_string();
+
+    let cycles = find_import_cycles(&analyze(root)?);
+    assert_eq!(cycles.len(), 1);
+    assert_eq!(cycles[0].files, vec![path("pkg/a.py"), path("pkg/b.py")]);
+    assert_eq!(
+        cycles[0].imports,
+        vec![
+            (
+                path("pkg/a.py"),
+                path("pkg/b.py"),
+                ImportSite {
+                    line: 2,
+                    kind: ImportKind::From
+                }
+            ),
+            (
+                path("pkg/b.py"),
+                path("pkg/a.py"),
+                ImportSite {
+                    line: 1,
+                    kind: ImportKind::RelativeFrom
+                }
+            ),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_cycles_baseline() -> Result<()> {
+    let temp_dir = create_project()?;
+    let root = temp_dir.path();
+    let baseline = root.join("cycles.txt");
+
+    write_baseline(&baseline, &find_import_cycles(&analyze(root)?), root)?;
+    let known = read_baseline(&baseline)?;
+    assert_eq!(
+        known,
+        vec![["pkg/a.py".to_string(), "pkg/b.py".to_string()].into()]
+    );
+    assert!(new_cycles(&find_import_cycles(&analyze(root)?), &known, root).is_empty());
+
+    // a known cycle growing to include another file is new
+    fs::write(root.join("pkg/b.py"), "from . import a\nfrom . import c\n")?;
+    let cycles = find_import_cycles(&analyze(root)?);
+    let new = new_cycles(&cycles, &known, root);
+    assert_eq!(new.len(), 1);
+    assert!(new.iter().all(|&i| cycles[i].files.len() == 3));
+    Ok(())
+}
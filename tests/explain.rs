@@ -0,0 +1,186 @@
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+use snob_lib::ast::{ImportKind, ImportSite, UnresolvedImport};
+use snob_lib::config::Config;
+use snob_lib::explain::{why, why_not};
+use snob_lib::pytest::TestFileMatcher;
+
+mod test_utils;
+use test_utils::analyze;
+
+fn create_project() -> Result<TempDir> {
+    test_utils::create_project(&[
+        ("app/__init__.py", ""),
+        ("app/tax.py", "RATE = 1\n"),
+        (
+            "app/orders.py",
+            "\"\"\"Orders\"\"\"\nimport os\n\nfrom .tax import RATE\n",
+        ),
+        ("app/billing.py", ""),
+        (
+            "tests/test_orders.py",
+            "import app.orders\nfrom app.billing import *\n",
+        ),
+    ])
+}
+
+fn path(root: &Path, file: &str) -> String {
+    root.join(file).to_string_lossy().to_string()
+}
+
+#[test]
+fn test_why_follows_the_import_statements() -> Result<()> {
+    let temp_dir = create_project()?;
+    let root = temp_dir.path();
+    let analysis = analyze(root)?;
+
+    let chains = why(
+        &analysis,
+        &path(root, "tests/test_orders.py"),
+        &[path(root, "app/tax.py")].into_iter().collect(),
+    );
+
+    assert_eq!(chains.len(), 1);
+    let steps = chains[0]
+        .iter()
+        .map(|step| {
+            (
+                step.file.strip_prefix(&path(root, "")).unwrap().to_string(),
+                step.site,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        steps,
+        vec![
+            ("app/tax.py".to_string(), None),
+            (
+                "app/orders.py".to_string(),
+                Some(ImportSite {
+                    line: 4,
+                    kind: ImportKind::RelativeFrom
+                })
+            ),
+            (
+                "tests/test_orders.py".to_string(),
+                Some(ImportSite {
+                    line: 1,
+                    kind: ImportKind::Import
+                })
+            ),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_why_has_one_chain_per_changed_dependency() -> Result<()> {
+    let temp_dir = create_project()?;
+    let root = temp_dir.path();
+    let analysis = analyze(root)?;
+
+    let changed = ["app/tax.py", "app/billing.py", "app/__init__.py"]
+        .iter()
+        .map(|f| path(root, f))
+        .collect();
+    let chains = why(&analysis, &path(root, "tests/test_orders.py"), &changed);
+
+    // `app/__init__.py` is not a dependency, the shortest chains come first
+    let starts = chains
+        .iter()
+        .map(|chain| (chain[0].file.clone(), chain.len()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        starts,
+        vec![
+            (path(root, "app/billing.py"), 2),
+            (path(root, "app/tax.py"), 3)
+        ]
+    );
+    assert_eq!(
+        chains[0][1].site,
+        Some(ImportSite {
+            line: 2,
+            kind: ImportKind::From
+        })
+    );
+    Ok(())
+}
+
+#[test]
+fn test_why_not_lists_what_breaks_the_chain() -> Result<()> {
+    let temp_dir = create_project()?;
+    let root = temp_dir.path();
+    fs::write(
+        root.join("tests/test_invoices.py"),
+        "import os\nfrom app.invoices_v2 import total\nimport app.generated\nimport app.broken\n",
+    )?;
+    fs::write(root.join("app/generated.py"), "import app.tax\n")?;
+    fs::write(root.join("app/broken.py"), "def broken(:\n")?;
+    fs::write(
+        root.join("snob.toml"),
+        "[files]\nignores = [\"app/generated.py\"]\n\n[tests]\nignores = [\"tests/test_invoices.py\"]\n",
+    )?;
+
+    let config = Config::new(root)?;
+    let analysis = analyze(root)?;
+    let test_matcher = TestFileMatcher::new(&config, root)?;
+    let why_not = why_not(
+        &analysis,
+        &config,
+        &test_matcher,
+        root,
+        &path(root, "tests/test_invoices.py"),
+        &[path(root, "app/tax.py")].into_iter().collect(),
+    )?;
+
+    assert!(why_not.impacted_by.is_empty());
+    assert!(!why_not.not_analyzed);
+    assert!(!why_not.not_a_test);
+    assert!(why_not.ignored_test);
+    assert_eq!(why_not.ignored_files, vec![path(root, "app/generated.py")]);
+    // third-party imports are not reported
+    assert_eq!(
+        why_not.unresolved_imports,
+        vec![(
+            path(root, "tests/test_invoices.py"),
+            UnresolvedImport {
+                import: "app.invoices_v2.total".to_string(),
+                site: ImportSite {
+                    line: 2,
+                    kind: ImportKind::From
+                }
+            }
+        )]
+    );
+    assert_eq!(why_not.parse_failures, vec![path(root, "app/broken.py")]);
+    Ok(())
+}
+
+#[test]
+fn test_why_not_impacted_test() -> Result<()> {
+    let temp_dir = create_project()?;
+    let root = temp_dir.path();
+
+    let config = Config::new(root)?;
+    let analysis = analyze(root)?;
+    let test_matcher = TestFileMatcher::new(&config, root)?;
+    let why_not = why_not(
+        &analysis,
+        &config,
+        &test_matcher,
+        root,
+        &path(root, "tests/test_orders.py"),
+        &[path(root, "app/tax.py")].into_iter().collect(),
+    )?;
+
+    assert_eq!(why_not.impacted_by, vec![path(root, "app/tax.py")]);
+    assert!(!why_not.ignored_test);
+    assert!(why_not.ignored_files.is_empty());
+    assert!(why_not.unresolved_imports.is_empty());
+    assert!(why_not.parse_failures.is_empty());
+    Ok(())
+}
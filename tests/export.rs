@@ -0,0 +1,163 @@
+use anyhow::Result;
+use rustc_hash::FxHashSet;
+use std::path::Path;
+use tempfile::TempDir;
+
+use snob_lib::analysis::Layout;
+use snob_lib::config::Config;
+use snob_lib::export::{ExportedGraph, GraphFormat, GraphNode};
+use snob_lib::pytest::TestFileMatcher;
+
+mod test_utils;
+use test_utils::analyze;
+
+fn create_project() -> Result<TempDir> {
+    test_utils::create_project(&[
+        ("app/__init__.py", ""),
+        ("app/tax.py", "RATE = 1\n"),
+        ("app/orders.py", "from .tax import RATE\n"),
+        ("app/broken.py", "import (\n"),
+        ("tests/test_orders.py", "import app.orders\n"),
+    ])
+}
+
+fn export(root: &Path, impacted_only: bool) -> Result<ExportedGraph> {
+    let config = Config::new(root)?;
+    let analysis = analyze(root)?;
+    let test_matcher = TestFileMatcher::new(&config, root)?;
+    let changed = FxHashSet::from_iter([root.join("app/tax.py").to_string_lossy().to_string()]);
+    let files = if impacted_only {
+        ["app/tax.py", "app/orders.py"]
+            .iter()
+            .map(|f| root.join(f).to_string_lossy().to_string())
+            .collect()
+    } else {
+        analysis.project_files.clone()
+    };
+    Ok(ExportedGraph::new(
+        &analysis,
+        &files,
+        &changed,
+        &test_matcher,
+        &Layout::new(root, root, root, None)?.lookup_paths,
+        root,
+    ))
+}
+
+#[test]
+fn test_export_node_attributes() -> Result<()> {
+    let temp_dir = create_project()?;
+    let graph = export(temp_dir.path(), false)?;
+
+    assert_eq!(
+        graph.nodes.keys().collect::<Vec<_>>(),
+        vec![
+            "app/__init__.py",
+            "app/broken.py",
+            "app/orders.py",
+            "app/tax.py",
+            "tests/test_orders.py"
+        ]
+    );
+    assert_eq!(
+        graph.nodes["app/tax.py"],
+        GraphNode {
+            module: "app.tax".to_string(),
+            package: "app".to_string(),
+            is_test: false,
+            is_changed: true,
+            parse_failed: false,
+            imports: vec![],
+        }
+    );
+    assert_eq!(graph.nodes["app/__init__.py"].package, "app");
+    assert!(graph.nodes["app/broken.py"].parse_failed);
+    assert!(graph.nodes["tests/test_orders.py"].is_test);
+    assert_eq!(
+        graph.nodes["tests/test_orders.py"].imports,
+        vec!["app/orders.py"]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_export_impacted_subgraph() -> Result<()> {
+    let temp_dir = create_project()?;
+    let graph = export(temp_dir.path(), true)?;
+
+    assert_eq!(graph.nodes.len(), 2);
+    assert_eq!(graph.nodes["app/orders.py"].imports, vec!["app/tax.py"]);
+    Ok(())
+}
+
+#[test]
+fn test_export_formats() -> Result<()> {
+    let temp_dir = create_project()?;
+    let graph = export(temp_dir.path(), true)?;
+
+    let mut mermaid = Vec::new();
+    graph.write(GraphFormat::Mermaid, &mut mermaid)?;
+    let mermaid = String::from_utf8(mermaid)?;
+    assert!(mermaid.starts_with("graph LR\n"));
+    assert!(mermaid.contains("    n0[\"app/orders.py\"]\n"));
+    assert!(mermaid.contains("    n1[\"app/tax.py\"]:::changed\n"));
+    assert!(mermaid.contains("    n0 --> n1\n"));
+
+    let mut graphml = Vec::new();
+    graph.write(GraphFormat::GraphMl, &mut graphml)?;
+    let graphml = String::from_utf8(graphml)?;
+    assert!(graphml.contains(r#"<edge source="app/orders.py" target="app/tax.py"/>"#));
+    assert!(graphml.contains(r#"<data key="is_changed">true</data>"#));
+
+    let mut json = Vec::new();
+    graph.write(GraphFormat::Json, &mut json)?;
+    let json: serde_json::Value = serde_json::from_slice(&json)?;
+    assert_eq!(
+        json["nodes"]["app/orders.py"]["imports"],
+        serde_json::json!(["app/tax.py"])
+    );
+    assert_eq!(
+        GraphFormat::from_extension(Path::new("deps.mmd")),
+        Some(GraphFormat::Mermaid)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_dot_clusters_and_colors() -> Result<()> {
+    let temp_dir = create_project()?;
+    let graph = export(temp_dir.path(), false)?;
+    let selected = FxHashSet::from_iter(["tests/test_orders.py".to_string()]);
+
+    let mut dot = Vec::new();
+    graph.write_dot(&selected, None, &mut dot)?;
+    let dot = String::from_utf8(dot)?;
+    assert!(dot.contains("        label=\"app\";\n"));
+    assert!(dot.contains("\"app/tax.py\" [label=\"app.tax\", fillcolor=\"#ff9966\"];"));
+    assert!(dot.contains("\"app/orders.py\" [label=\"app.orders\", fillcolor=\"#ffe699\"];"));
+    assert!(dot.contains(
+        "\"tests/test_orders.py\" [label=\"tests.test_orders\", fillcolor=\"#99ccff\"];"
+    ));
+    assert!(dot.contains("    \"app/orders.py\" -> \"app/tax.py\";\n"));
+    Ok(())
+}
+
+#[test]
+fn test_dot_collapses_large_packages() -> Result<()> {
+    let temp_dir = create_project()?;
+    let graph = export(temp_dir.path(), false)?;
+
+    let mut dot = Vec::new();
+    graph.write_dot(&FxHashSet::default(), Some(3), &mut dot)?;
+    let dot = String::from_utf8(dot)?;
+    // app holds 4 files, tests a single one
+    assert!(dot.contains(
+        "    \"package:app\" [label=\"app (4 files)\", shape=folder, fillcolor=\"#ff9966\"];\n"
+    ));
+    assert!(dot
+        .contains("\"tests/test_orders.py\" [label=\"tests.test_orders\", fillcolor=\"white\"];"));
+    // the imports within app are gone
+    assert_eq!(dot.matches(" -> ").count(), 1);
+    assert!(dot.contains("    \"tests/test_orders.py\" -> \"package:app\";\n"));
+    Ok(())
+}
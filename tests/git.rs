@@ -0,0 +1,726 @@
+use anyhow::Result;
+use rustc_hash::FxHashSet;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+use snob_lib::analysis::Layout;
+use snob_lib::commits::{commits_touching_test, impacted_tests_per_commit, union, CommitImpact};
+use snob_lib::config::Config;
+use snob_lib::git::{get_changed_files, parse_name_status, ChangeSelection};
+use snob_lib::source::{GitRevision, SourceProvider, WorkingTree};
+use snob_lib::{get_impacted_tests_from_changed_files, get_impacted_tests_from_source, SnobOutput};
+
+fn git(repo: &Path, args: &[&str]) -> Result<()> {
+    let status = Command::new("git")
+        .args(["-c", "user.name=snob", "-c", "user.email=snob@example.com"])
+        .args(args)
+        .current_dir(repo)
+        .output()?
+        .status;
+    anyhow::ensure!(status.success(), "git {:?} failed", args);
+    Ok(())
+}
+
+/// Creates a repository with a `main` branch, a `feature` branch that diverged from it,
+/// and some staged, unstaged and untracked changes on top of `feature`
+fn create_repository() -> Result<TempDir> {
+    let temp_dir = TempDir::new()?;
+    let repo = temp_dir.path();
+
+    git(repo, &["init", "-q", "-b", "main"])?;
+    fs::create_dir_all(repo.join("app"))?;
+    fs::write(repo.join("app/__init__.py"), "")?;
+    fs::write(repo.join("app/models.py"), "")?;
+    fs::write(repo.join("app/views.py"), "")?;
+    fs::write(repo.join("app/removed.py"), "")?;
+    fs::write(repo.join(".gitignore"), "*.log\n")?;
+    git(repo, &["add", "."])?;
+    git(repo, &["commit", "-q", "-m", "initial"])?;
+
+    git(repo, &["checkout", "-q", "-b", "feature"])?;
+    fs::write(repo.join("app/models.py"), "class Model: pass\n")?;
+    git(repo, &["rm", "-q", "app/removed.py"])?;
+    git(repo, &["commit", "-q", "-am", "feature"])?;
+
+    // main moves on, which should not show up in the feature's changes
+    git(repo, &["checkout", "-q", "main"])?;
+    fs::write(repo.join("app/main_only.py"), "")?;
+    git(repo, &["add", "."])?;
+    git(repo, &["commit", "-q", "-m", "main"])?;
+    git(repo, &["checkout", "-q", "feature"])?;
+
+    fs::write(repo.join("app/__init__.py"), "from app import views\n")?;
+    git(repo, &["add", "app/__init__.py"])?;
+    fs::write(repo.join("app/views.py"), "def view(): pass\n")?;
+    fs::write(repo.join("app/new file.py"), "")?;
+    fs::write(repo.join("debug.log"), "")?;
+
+    Ok(temp_dir)
+}
+
+fn changed_files(repo: &Path, selection: &ChangeSelection) -> Result<Vec<String>> {
+    Ok(get_changed_files(repo, selection)?
+        .into_iter()
+        .map(|f| {
+            Path::new(&f)
+                .strip_prefix(repo)
+                .unwrap()
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect())
+}
+
+#[test]
+fn test_changes_since_merge_base() -> Result<()> {
+    let temp_dir = create_repository()?;
+    let selection = ChangeSelection {
+        base: Some("main".to_string()),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        changed_files(temp_dir.path(), &selection)?,
+        vec!["app/models.py", "app/removed.py"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_working_tree_changes() -> Result<()> {
+    let temp_dir = create_repository()?;
+    let repo = temp_dir.path();
+
+    let staged = ChangeSelection {
+        staged: true,
+        ..Default::default()
+    };
+    assert_eq!(changed_files(repo, &staged)?, vec!["app/__init__.py"]);
+
+    let unstaged = ChangeSelection {
+        unstaged: true,
+        ..Default::default()
+    };
+    assert_eq!(changed_files(repo, &unstaged)?, vec!["app/views.py"]);
+
+    let untracked = ChangeSelection {
+        untracked: true,
+        ..Default::default()
+    };
+    assert_eq!(changed_files(repo, &untracked)?, vec!["app/new file.py"]);
+
+    Ok(())
+}
+
+#[test]
+fn test_combined_changes() -> Result<()> {
+    let temp_dir = create_repository()?;
+    let selection = ChangeSelection {
+        base: Some("main".to_string()),
+        staged: true,
+        unstaged: true,
+        untracked: true,
+        revision: None,
+    };
+
+    assert_eq!(
+        changed_files(temp_dir.path(), &selection)?,
+        vec![
+            "app/__init__.py",
+            "app/models.py",
+            "app/new file.py",
+            "app/removed.py",
+            "app/views.py"
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_unknown_base_is_an_error() -> Result<()> {
+    let temp_dir = create_repository()?;
+    let selection = ChangeSelection {
+        base: Some("does-not-exist".to_string()),
+        ..Default::default()
+    };
+
+    assert!(get_changed_files(temp_dir.path(), &selection).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_project_below_the_git_root() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let repo = temp_dir.path();
+    let project = repo.join("packages/api");
+
+    git(repo, &["init", "-q", "-b", "main"])?;
+    fs::create_dir_all(&project)?;
+    fs::write(
+        project.join("pyproject.toml"),
+        "[project]\nname = \"api\"\n",
+    )?;
+    fs::write(project.join("models.py"), "")?;
+    fs::write(project.join("views.py"), "")?;
+    fs::write(repo.join("other.py"), "")?;
+    git(repo, &["add", "."])?;
+    git(repo, &["commit", "-q", "-m", "initial"])?;
+
+    git(repo, &["checkout", "-q", "-b", "feature"])?;
+    fs::write(project.join("models.py"), "class Model: pass\n")?;
+    fs::write(repo.join("other.py"), "x = 1\n")?;
+    git(repo, &["commit", "-q", "-am", "feature"])?;
+    fs::write(project.join("views.py"), "def view(): pass\n")?;
+    fs::write(project.join("new.py"), "")?;
+
+    // the changes are relative to the project, the ones outside of it are left out
+    let selection = ChangeSelection {
+        base: Some("main".to_string()),
+        unstaged: true,
+        untracked: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        changed_files(&project, &selection)?,
+        vec!["models.py", "new.py", "views.py"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_name_status() {
+    let output = "M\0app/views.py\0D\0app/gone.py\0R086\0app/old.py\0app/new.py\0A\0app/added.py\0";
+
+    assert_eq!(
+        parse_name_status(output),
+        vec![
+            "app/views.py",
+            "app/gone.py",
+            "app/old.py",
+            "app/new.py",
+            "app/added.py"
+        ]
+    );
+}
+
+#[test]
+fn test_deleted_and_renamed_modules_impact_their_consumers() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let repo = temp_dir.path();
+
+    git(repo, &["init", "-q", "-b", "main"])?;
+    fs::create_dir_all(repo.join("app"))?;
+    fs::create_dir_all(repo.join("tests"))?;
+    fs::write(repo.join("app/__init__.py"), "")?;
+    fs::write(repo.join("app/gone.py"), "def gone(): pass\n")?;
+    fs::write(repo.join("app/legacy.py"), "def legacy(): pass\n")?;
+    fs::write(repo.join("app/unrelated.py"), "")?;
+    fs::write(
+        repo.join("tests/test_gone.py"),
+        "from app.gone import gone\n",
+    )?;
+    fs::write(
+        repo.join("tests/test_legacy.py"),
+        "from app.legacy import legacy\n",
+    )?;
+    fs::write(repo.join("tests/test_removed.py"), "from app import gone\n")?;
+    fs::write(
+        repo.join("tests/test_unrelated.py"),
+        "from app.unrelated import *\n",
+    )?;
+    git(repo, &["add", "."])?;
+    git(repo, &["commit", "-q", "-m", "initial"])?;
+
+    git(repo, &["rm", "-q", "app/gone.py", "tests/test_removed.py"])?;
+    git(repo, &["mv", "app/legacy.py", "app/modern.py"])?;
+    fs::write(
+        repo.join("tests/test_modern.py"),
+        "from app.modern import legacy\n",
+    )?;
+    git(repo, &["add", "."])?;
+
+    let selection = ChangeSelection {
+        staged: true,
+        ..Default::default()
+    };
+    let changed = get_changed_files(repo, &selection)?
+        .into_iter()
+        .collect::<FxHashSet<String>>();
+
+    let root = repo.to_path_buf();
+    let config = Config::new(&root)?;
+    let SnobOutput::Partial(results) =
+        get_impacted_tests_from_changed_files(&config, &root, &root, &changed)?
+    else {
+        panic!("Expected partial results");
+    };
+
+    let mut impacted = results
+        .impacted
+        .iter()
+        .map(|t| {
+            Path::new(t)
+                .strip_prefix(repo)
+                .unwrap()
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect::<Vec<_>>();
+    impacted.sort();
+
+    // the deleted test itself can't be run anymore
+    assert_eq!(
+        impacted,
+        vec![
+            "tests/test_gone.py",
+            "tests/test_legacy.py",
+            "tests/test_modern.py"
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_deleted_top_level_modules_impact_their_consumers() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let repo = temp_dir.path();
+
+    git(repo, &["init", "-q", "-b", "main"])?;
+    fs::create_dir_all(repo.join("tests"))?;
+    fs::create_dir_all(repo.join("legacy"))?;
+    fs::write(repo.join("settings.py"), "DEBUG = True\n")?;
+    fs::write(repo.join("legacy/__init__.py"), "")?;
+    fs::write(repo.join("legacy/api.py"), "")?;
+    fs::write(repo.join("tests/test_settings.py"), "import settings\n")?;
+    fs::write(
+        repo.join("tests/test_legacy.py"),
+        "from legacy.api import *\n",
+    )?;
+    fs::write(repo.join("tests/test_other.py"), "")?;
+    git(repo, &["add", "."])?;
+    git(repo, &["commit", "-q", "-m", "initial"])?;
+
+    // the whole package goes away, along with its directory
+    git(repo, &["rm", "-q", "-r", "settings.py", "legacy"])?;
+    assert!(!repo.join("legacy").exists());
+
+    let changed = ["settings.py", "legacy/__init__.py", "legacy/api.py"]
+        .iter()
+        .map(|f| repo.join(f).to_string_lossy().to_string())
+        .collect::<FxHashSet<String>>();
+    let root = repo.to_path_buf();
+    let config = Config::new(&root)?;
+    let SnobOutput::Partial(results) =
+        get_impacted_tests_from_changed_files(&config, &root, &root, &changed)?
+    else {
+        panic!("Expected partial results");
+    };
+
+    let mut impacted = results
+        .impacted
+        .iter()
+        .map(|t| {
+            Path::new(t)
+                .strip_prefix(repo)
+                .unwrap()
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect::<Vec<_>>();
+    impacted.sort();
+
+    assert_eq!(
+        impacted,
+        vec!["tests/test_legacy.py", "tests/test_settings.py"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_analyze_revision_without_checking_it_out() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let repo = temp_dir.path();
+    let root = repo.to_path_buf();
+
+    git(repo, &["init", "-q", "-b", "main"])?;
+    fs::create_dir_all(repo.join("app"))?;
+    fs::create_dir_all(repo.join("tests"))?;
+    fs::write(repo.join("app/__init__.py"), "")?;
+    fs::write(repo.join("app/old.py"), "")?;
+    fs::write(repo.join("tests/test_app.py"), "from app.old import *\n")?;
+    git(repo, &["add", "."])?;
+    git(repo, &["commit", "-q", "-m", "initial"])?;
+
+    fs::create_dir_all(repo.join("app/new"))?;
+    fs::write(repo.join("app/new/__init__.py"), "")?;
+    fs::write(repo.join("tests/test_app.py"), "from app.new import *\n")?;
+    git(repo, &["add", "."])?;
+    git(repo, &["commit", "-q", "-m", "new"])?;
+    git(repo, &["tag", "new"])?;
+
+    // the working tree is back at the initial revision
+    git(repo, &["checkout", "-q", "HEAD~1"])?;
+    assert!(!repo.join("app/new").exists());
+
+    let revision = GitRevision::new(repo, "new")?;
+    assert!(revision.exists(&repo.join("app/new/__init__.py")));
+    assert_eq!(
+        revision.read_to_string(&repo.join("tests/test_app.py"))?,
+        "from app.new import *\n"
+    );
+
+    let changed = [repo
+        .join("app/new/__init__.py")
+        .to_string_lossy()
+        .to_string()]
+    .into_iter()
+    .collect::<FxHashSet<String>>();
+    let config = Config::new(&root)?;
+    let SnobOutput::Partial(results) =
+        get_impacted_tests_from_source(&config, &root, &root, &changed, &revision, None)?
+    else {
+        panic!("Expected partial results");
+    };
+
+    assert_eq!(
+        results.impacted.into_iter().collect::<Vec<_>>(),
+        vec![repo.join("tests/test_app.py").to_string_lossy().to_string()]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_union_with_base_graph() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let repo = temp_dir.path();
+    let root = repo.to_path_buf();
+
+    git(repo, &["init", "-q", "-b", "main"])?;
+    fs::create_dir_all(repo.join("app"))?;
+    fs::create_dir_all(repo.join("tests"))?;
+    fs::write(repo.join("app/__init__.py"), "")?;
+    fs::write(repo.join("app/billing.py"), "def bill(): pass\n")?;
+    fs::write(repo.join("app/orders.py"), "from app.billing import bill\n")?;
+    fs::write(
+        repo.join("tests/test_orders.py"),
+        "from app.orders import *\n",
+    )?;
+    git(repo, &["add", "."])?;
+    git(repo, &["commit", "-q", "-m", "initial"])?;
+
+    // `orders` stops depending on `billing`
+    fs::write(repo.join("app/orders.py"), "def order(): pass\n")?;
+    fs::write(repo.join("app/billing.py"), "def bill(amount): pass\n")?;
+    git(repo, &["commit", "-q", "-am", "decouple"])?;
+
+    let changed = [repo.join("app/billing.py").to_string_lossy().to_string()]
+        .into_iter()
+        .collect::<FxHashSet<String>>();
+    let config = Config::new(&root)?;
+
+    let SnobOutput::Partial(results) =
+        get_impacted_tests_from_changed_files(&config, &root, &root, &changed)?
+    else {
+        panic!("Expected partial results");
+    };
+    assert!(results.impacted.is_empty());
+
+    let base = GitRevision::new(repo, "HEAD~1")?;
+    let SnobOutput::Partial(results) =
+        get_impacted_tests_from_source(&config, &root, &root, &changed, &WorkingTree, Some(&base))?
+    else {
+        panic!("Expected partial results");
+    };
+    assert_eq!(
+        results.impacted.into_iter().collect::<Vec<_>>(),
+        vec![repo
+            .join("tests/test_orders.py")
+            .to_string_lossy()
+            .to_string()]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_impacted_tests_per_commit() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let repo = temp_dir.path();
+    let root = repo.to_path_buf();
+
+    git(repo, &["init", "-q", "-b", "main"])?;
+    fs::create_dir_all(repo.join("app"))?;
+    fs::create_dir_all(repo.join("tests"))?;
+    fs::write(repo.join("app/__init__.py"), "")?;
+    fs::write(repo.join("app/billing.py"), "")?;
+    fs::write(repo.join("app/orders.py"), "")?;
+    fs::write(
+        repo.join("tests/test_billing.py"),
+        "from app.billing import *\n",
+    )?;
+    fs::write(
+        repo.join("tests/test_orders.py"),
+        "from app.orders import *\n",
+    )?;
+    git(repo, &["add", "."])?;
+    git(repo, &["commit", "-q", "-m", "initial"])?;
+    git(repo, &["tag", "start"])?;
+
+    fs::write(repo.join("app/billing.py"), "def bill(): pass\n")?;
+    git(repo, &["commit", "-q", "-am", "billing"])?;
+    fs::write(repo.join("README.md"), "docs\n")?;
+    git(repo, &["add", "."])?;
+    git(repo, &["commit", "-q", "-m", "docs"])?;
+    fs::write(repo.join("app/orders.py"), "def order(): pass\n")?;
+    git(repo, &["commit", "-q", "-am", "orders"])?;
+
+    let config = Config::new(&root)?;
+    let layout = Layout::new(&root, &root, &root, None)?;
+    let impacts = impacted_tests_per_commit(&config, &layout, &root, "start..HEAD")?;
+
+    let relative = |files: &[String]| {
+        files
+            .iter()
+            .map(|f| {
+                Path::new(f)
+                    .strip_prefix(repo)
+                    .unwrap()
+                    .display()
+                    .to_string()
+            })
+            .collect::<Vec<_>>()
+    };
+    let impacted = |impact: &CommitImpact| {
+        relative(
+            &impact
+                .impacted
+                .clone()
+                .unwrap()
+                .into_iter()
+                .collect::<Vec<_>>(),
+        )
+    };
+
+    assert_eq!(
+        impacts
+            .iter()
+            .map(|i| i.summary.as_str())
+            .collect::<Vec<_>>(),
+        vec!["billing", "docs", "orders"]
+    );
+    assert_eq!(relative(&impacts[0].changed), vec!["app/billing.py"]);
+    assert_eq!(impacted(&impacts[0]), vec!["tests/test_billing.py"]);
+    assert_eq!(relative(&impacts[1].changed), vec!["README.md"]);
+    assert!(impacted(&impacts[1]).is_empty());
+    assert_eq!(impacted(&impacts[2]), vec!["tests/test_orders.py"]);
+    assert_eq!(
+        relative(&union(&impacts).unwrap().into_iter().collect::<Vec<_>>()),
+        vec!["tests/test_billing.py", "tests/test_orders.py"]
+    );
+
+    Ok(())
+}
+
+/// Creates a repository whose `packages/api` project has a test for its `models.py`,
+/// the `start` tag is followed by a commit changing `models.py` along with a file outside of
+/// the project
+fn create_monorepo() -> Result<TempDir> {
+    let temp_dir = TempDir::new()?;
+    let repo = temp_dir.path();
+    let project = repo.join("packages/api");
+
+    git(repo, &["init", "-q", "-b", "main"])?;
+    fs::create_dir_all(project.join("tests"))?;
+    fs::write(project.join("models.py"), "")?;
+    fs::write(project.join("tests/test_models.py"), "import models\n")?;
+    fs::write(repo.join("other.py"), "")?;
+    git(repo, &["add", "."])?;
+    git(repo, &["commit", "-q", "-m", "initial"])?;
+    git(repo, &["tag", "start"])?;
+
+    fs::write(project.join("models.py"), "class Model: pass\n")?;
+    fs::write(repo.join("other.py"), "x = 1\n")?;
+    git(repo, &["commit", "-q", "-am", "models"])?;
+
+    Ok(temp_dir)
+}
+
+#[test]
+fn test_impacted_tests_per_commit_below_the_git_root() -> Result<()> {
+    let temp_dir = create_monorepo()?;
+    let root = temp_dir.path().join("packages/api");
+
+    let config = Config::new(&root)?;
+    let layout = Layout::new(&root, &root, &root, None)?;
+    let impacts = impacted_tests_per_commit(&config, &layout, &root, "start..HEAD")?;
+
+    assert_eq!(impacts.len(), 1);
+    assert_eq!(
+        impacts[0].changed,
+        vec![root.join("models.py").to_string_lossy().to_string()]
+    );
+    assert_eq!(
+        impacts[0].impacted,
+        Some(
+            [root
+                .join("tests/test_models.py")
+                .to_string_lossy()
+                .to_string()]
+            .into_iter()
+            .collect()
+        )
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_commits_touching_test_dependency_closure() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let repo = temp_dir.path();
+    let root = repo.to_path_buf();
+
+    git(repo, &["init", "-q", "-b", "main"])?;
+    fs::create_dir_all(repo.join("app"))?;
+    fs::create_dir_all(repo.join("tests"))?;
+    fs::write(repo.join("app/__init__.py"), "")?;
+    fs::write(repo.join("app/tax.py"), "")?;
+    fs::write(repo.join("app/orders.py"), "from app.tax import *\n")?;
+    fs::write(repo.join("app/billing.py"), "from app.orders import *\n")?;
+    fs::write(
+        repo.join("tests/test_orders.py"),
+        "from app.orders import *\n",
+    )?;
+    git(repo, &["add", "."])?;
+    git(repo, &["commit", "-q", "-m", "initial"])?;
+    git(repo, &["tag", "start"])?;
+
+    for (file, summary) in [
+        ("app/billing.py", "billing"),
+        ("app/tax.py", "tax"),
+        ("README.md", "docs"),
+        ("tests/test_orders.py", "test"),
+    ] {
+        fs::write(repo.join(file), "# changed\n")?;
+        git(repo, &["add", "."])?;
+        git(repo, &["commit", "-q", "-m", summary])?;
+    }
+    // the closure is computed on the working tree
+    fs::write(
+        repo.join("tests/test_orders.py"),
+        "from app.orders import *\n",
+    )?;
+
+    let config = Config::new(&root)?;
+    let layout = Layout::new(&root, &root, &root, None)?;
+    let test = repo
+        .join("tests/test_orders.py")
+        .to_string_lossy()
+        .to_string();
+    let commits = commits_touching_test(&config, &layout, &root, &test, "start..HEAD")?;
+
+    assert_eq!(
+        commits
+            .iter()
+            .map(|c| (c.summary.as_str(), c.touched.clone()))
+            .collect::<Vec<_>>(),
+        vec![
+            (
+                "tax",
+                vec![repo.join("app/tax.py").to_string_lossy().to_string()]
+            ),
+            ("test", vec![test.clone()]),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_commits_touching_test_below_the_git_root() -> Result<()> {
+    let temp_dir = create_monorepo()?;
+    let root = temp_dir.path().join("packages/api");
+
+    let config = Config::new(&root)?;
+    let layout = Layout::new(&root, &root, &root, None)?;
+    let test = root
+        .join("tests/test_models.py")
+        .to_string_lossy()
+        .to_string();
+    let commits = commits_touching_test(&config, &layout, &root, &test, "start..HEAD")?;
+
+    assert_eq!(
+        commits
+            .iter()
+            .map(|c| (c.summary.as_str(), c.touched.clone()))
+            .collect::<Vec<_>>(),
+        vec![(
+            "models",
+            vec![root.join("models.py").to_string_lossy().to_string()]
+        )]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_changes_of_submodules() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let library = temp_dir.path().join("library");
+    let repo = temp_dir.path().join("repository");
+    fs::create_dir_all(&library)?;
+    fs::create_dir_all(&repo)?;
+
+    git(&library, &["init", "-q", "-b", "main"])?;
+    fs::write(library.join("lib.py"), "")?;
+    git(&library, &["add", "."])?;
+    git(&library, &["commit", "-q", "-m", "initial"])?;
+
+    git(&repo, &["init", "-q", "-b", "main"])?;
+    fs::write(repo.join("main.py"), "")?;
+    git(
+        &repo,
+        &[
+            "-c",
+            "protocol.file.allow=always",
+            "submodule",
+            "add",
+            "-q",
+            &library.to_string_lossy(),
+            "vendor/library",
+        ],
+    )?;
+    git(&repo, &["add", "."])?;
+    git(&repo, &["commit", "-q", "-m", "initial"])?;
+
+    // a new commit of the submodule, recorded in the superproject
+    let submodule = repo.join("vendor/library");
+    fs::write(submodule.join("lib.py"), "def f(): pass\n")?;
+    git(&submodule, &["commit", "-q", "-am", "lib"])?;
+    git(&repo, &["commit", "-q", "-am", "bump library"])?;
+    // and uncommitted work in the submodule
+    fs::write(submodule.join("other.py"), "")?;
+
+    assert_eq!(
+        changed_files(
+            &repo,
+            &ChangeSelection {
+                base: Some("HEAD~1".to_string()),
+                untracked: true,
+                ..Default::default()
+            }
+        )?,
+        vec!["vendor/library/lib.py", "vendor/library/other.py"]
+    );
+
+    Ok(())
+}
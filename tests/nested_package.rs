@@ -25,7 +25,7 @@ fn test_nested_package_deep_module_changes() -> Result<()> {
     .into_iter()
     .collect::<FxHashSet<String>>();
 
-    let config = Config::new(&project_path);
+    let config = Config::new(&project_path)?;
     let result = get_impacted_tests_from_changed_files(
         &config,
         &project_path,
@@ -73,7 +73,7 @@ fn test_nested_package_top_module_changes() -> Result<()> {
     .into_iter()
     .collect::<FxHashSet<String>>();
 
-    let config = Config::new(&project_path);
+    let config = Config::new(&project_path)?;
     let result = get_impacted_tests_from_changed_files(
         &config,
         &project_path,
@@ -116,7 +116,7 @@ fn test_nested_package_isolation() -> Result<()> {
     // Test with no changes
     let changed_files = FxHashSet::default();
 
-    let config = Config::new(&project_path);
+    let config = Config::new(&project_path)?;
     let result = get_impacted_tests_from_changed_files(
         &config,
         &project_path,
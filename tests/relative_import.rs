@@ -25,7 +25,7 @@ fn test_relative_import_base_module_changes() -> Result<()> {
     .into_iter()
     .collect::<FxHashSet<String>>();
 
-    let config = Config::new(&project_path);
+    let config = Config::new(&project_path)?;
     let result = get_impacted_tests_from_changed_files(
         &config,
         &project_path,
@@ -73,7 +73,7 @@ fn test_relative_import_consumer_changes() -> Result<()> {
     .into_iter()
     .collect::<FxHashSet<String>>();
 
-    let config = Config::new(&project_path);
+    let config = Config::new(&project_path)?;
     let result = get_impacted_tests_from_changed_files(
         &config,
         &project_path,
@@ -121,7 +121,7 @@ fn test_relative_import_sibling_changes() -> Result<()> {
     .into_iter()
     .collect::<FxHashSet<String>>();
 
-    let config = Config::new(&project_path);
+    let config = Config::new(&project_path)?;
     let result = get_impacted_tests_from_changed_files(
         &config,
         &project_path,
@@ -175,7 +175,7 @@ fn test_relative_import_dependency_chain() -> Result<()> {
     .into_iter()
     .collect::<FxHashSet<String>>();
 
-    let config = Config::new(&project_path);
+    let config = Config::new(&project_path)?;
     let result = get_impacted_tests_from_changed_files(
         &config,
         &project_path,
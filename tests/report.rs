@@ -0,0 +1,77 @@
+use anyhow::Result;
+use rustc_hash::FxHashSet;
+
+use snob_lib::analysis::Layout;
+use snob_lib::config::Config;
+use snob_lib::fs::build_glob_set;
+use snob_lib::graph::discover_impacted_nodes_with_predecessors;
+use snob_lib::pytest::TestFileMatcher;
+use snob_lib::report::Report;
+use snob_lib::results::SnobResult;
+
+mod test_utils;
+use test_utils::analyze;
+
+#[test]
+fn test_report() -> Result<()> {
+    let temp_dir = test_utils::create_project(&[
+        ("app/__init__.py", ""),
+        ("app/tax.py", "RATE = 1\n"),
+        ("app/orders.py", "from .tax import RATE\n"),
+        ("tests/test_orders.py", "import app.orders\n"),
+        ("tests/test_other.py", ""),
+    ])?;
+    let root = temp_dir.path();
+
+    let config = Config::new(root)?;
+    let layout = Layout::new(root, root, root, None)?;
+    let analysis = analyze(root)?;
+    let changed = FxHashSet::from_iter([root.join("app/tax.py").to_string_lossy().to_string()]);
+    let impacted_nodes =
+        discover_impacted_nodes_with_predecessors(&analysis.dependency_graph, &changed);
+    let test_matcher = TestFileMatcher::new(&config, root)?;
+    let snob_results = SnobResult::new(
+        impacted_nodes.nodes().cloned().collect(),
+        analysis.project_files.clone(),
+        &build_glob_set(&FxHashSet::default())?,
+        &build_glob_set(&FxHashSet::default())?,
+        &test_matcher,
+        root,
+    );
+
+    let report = Report::new(
+        &analysis,
+        &impacted_nodes,
+        &changed,
+        &snob_results,
+        &test_matcher,
+        &layout.lookup_paths,
+        root,
+    );
+    assert_eq!(report.summary.impacted_modules, 2);
+    assert_eq!(report.summary.selected_tests, 1);
+    assert_eq!(report.summary.total_tests, 2);
+    assert_eq!(report.depths["tests/test_orders.py"], 2);
+    let chain = &report.paths["tests/test_orders.py"][0];
+    assert_eq!(
+        chain
+            .iter()
+            .map(|step| (step.file.as_str(), step.line))
+            .collect::<Vec<_>>(),
+        vec![
+            ("app/tax.py", None),
+            ("app/orders.py", Some(1)),
+            ("tests/test_orders.py", Some(1))
+        ]
+    );
+
+    let mut html = Vec::new();
+    report.write_html(&mut html)?;
+    let html = String::from_utf8(html)?;
+    assert!(html.contains(r#""selected":["tests/test_orders.py"]"#));
+    // a single page, without any network resource
+    assert!(!html.contains(" src="));
+    assert!(!html.contains("<link"));
+    assert_eq!(html.matches("</script>").count(), 2);
+    Ok(())
+}
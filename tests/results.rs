@@ -0,0 +1,203 @@
+use anyhow::Result;
+use rustc_hash::FxHashSet;
+use std::collections::BTreeSet;
+use tempfile::TempDir;
+
+use snob_lib::config::Config;
+use snob_lib::fs::build_glob_set;
+use snob_lib::pytest::TestFileMatcher;
+use snob_lib::results::{impacted_modules, SnobResult};
+
+const TESTS: [&str; 5] = [
+    "tests/test_plain.py",
+    "tests/test_ignored.py",
+    "tests/test_health.py",
+    "tests/test_ignored_health.py",
+    "tests/test_slow_health.py",
+];
+
+fn globs(globs: &[&str]) -> Result<globset::GlobSet> {
+    build_glob_set(&globs.iter().map(ToString::to_string).collect())
+}
+
+fn relative(root: &std::path::Path, files: impl IntoIterator<Item = String>) -> BTreeSet<String> {
+    files
+        .into_iter()
+        .map(|f| {
+            f.strip_prefix(&format!("{}/", root.display()))
+                .unwrap()
+                .to_string()
+        })
+        .collect()
+}
+
+/// Runs the selection with:
+/// - impacted: `test_plain`, `test_ignored`, `test_ignored_health` and `app/health.py`
+/// - ignored: `test_ignored*` and `test_slow*`
+/// - always run: `tests/*health*`
+fn select() -> Result<(TempDir, SnobResult)> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+    let path = |f: &str| root.join(f).to_string_lossy().to_string();
+
+    let workspace_files = TESTS
+        .iter()
+        .chain(&["app/health.py"])
+        .map(|f| path(f))
+        .collect::<FxHashSet<_>>();
+    let impacted = [
+        "tests/test_plain.py",
+        "tests/test_ignored.py",
+        "tests/test_ignored_health.py",
+        "app/health.py",
+    ]
+    .iter()
+    .map(|f| path(f))
+    .collect::<FxHashSet<_>>();
+
+    let result = SnobResult::new(
+        impacted,
+        workspace_files,
+        &globs(&["tests/test_ignored*.py", "tests/test_slow*.py"])?,
+        &globs(&["tests/*health*.py"])?,
+        &TestFileMatcher::new(&Config::new(root)?, root)?,
+        root,
+    );
+    Ok((temp_dir, result))
+}
+
+#[test]
+fn test_impacted_tests_are_selected() -> Result<()> {
+    let (temp_dir, result) = select()?;
+    assert!(relative(temp_dir.path(), result.selected()).contains("tests/test_plain.py"));
+    Ok(())
+}
+
+#[test]
+fn test_ignored_tests_are_not_selected() -> Result<()> {
+    let (temp_dir, result) = select()?;
+    let root = temp_dir.path();
+    assert!(!relative(root, result.selected()).contains("tests/test_ignored.py"));
+    assert_eq!(
+        relative(root, result.ignored.clone()),
+        BTreeSet::from(["tests/test_ignored.py".to_string()])
+    );
+    Ok(())
+}
+
+#[test]
+fn test_always_run_tests_are_selected_without_being_impacted() -> Result<()> {
+    let (temp_dir, result) = select()?;
+    assert!(relative(temp_dir.path(), result.selected()).contains("tests/test_health.py"));
+    Ok(())
+}
+
+#[test]
+fn test_always_run_wins_over_ignores() -> Result<()> {
+    let (temp_dir, result) = select()?;
+    let root = temp_dir.path();
+    let selected = relative(root, result.selected());
+    // impacted, ignored and always run
+    assert!(selected.contains("tests/test_ignored_health.py"));
+    // not impacted, ignored and always run
+    assert!(selected.contains("tests/test_slow_health.py"));
+    assert!(!relative(root, result.ignored.clone()).contains("tests/test_ignored_health.py"));
+    Ok(())
+}
+
+#[test]
+fn test_always_run_files_are_selected_without_matching_test_discovery() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+    let path = |f: &str| root.join(f).to_string_lossy().to_string();
+
+    let workspace_files = ["tests/health_check.py", "tests/test_plain.py"]
+        .iter()
+        .map(|f| path(f))
+        .collect::<FxHashSet<_>>();
+    let result = SnobResult::new(
+        FxHashSet::default(),
+        workspace_files,
+        &globs(&[])?,
+        &globs(&["tests/health_check.py"])?,
+        &TestFileMatcher::new(&Config::new(root)?, root)?,
+        root,
+    );
+
+    assert_eq!(
+        relative(root, result.selected()),
+        BTreeSet::from(["tests/health_check.py".to_string()])
+    );
+    Ok(())
+}
+
+#[test]
+fn test_only_tests_are_selected() -> Result<()> {
+    let (temp_dir, result) = select()?;
+    assert_eq!(
+        relative(temp_dir.path(), result.selected()),
+        BTreeSet::from([
+            "tests/test_health.py".to_string(),
+            "tests/test_ignored_health.py".to_string(),
+            "tests/test_plain.py".to_string(),
+            "tests/test_slow_health.py".to_string(),
+        ])
+    );
+    Ok(())
+}
+
+#[test]
+fn test_impacted_modules() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+    let path = |f: &str| root.join(f).to_string_lossy().to_string();
+
+    let project_files = [
+        "app/orders.py",
+        "app/tax.py",
+        "lib/io.py",
+        "tests/test_orders.py",
+    ]
+    .iter()
+    .map(|f| path(f))
+    .collect::<FxHashSet<_>>();
+    // README.md was changed, but isn't a python file
+    let impacted = [
+        "README.md",
+        "app/orders.py",
+        "lib/io.py",
+        "tests/test_orders.py",
+    ]
+    .iter()
+    .map(|f| path(f))
+    .collect::<FxHashSet<_>>();
+    let test_matcher = TestFileMatcher::new(&Config::new(root)?, root)?;
+    let app = globs(&["app/**"])?;
+
+    assert_eq!(
+        relative(
+            root,
+            impacted_modules(&impacted, &project_files, None, None, root)
+        ),
+        BTreeSet::from([
+            "app/orders.py".to_string(),
+            "lib/io.py".to_string(),
+            "tests/test_orders.py".to_string(),
+        ])
+    );
+    assert_eq!(
+        relative(
+            root,
+            impacted_modules(&impacted, &project_files, Some(&test_matcher), None, root)
+        ),
+        BTreeSet::from(["app/orders.py".to_string(), "lib/io.py".to_string()])
+    );
+    assert_eq!(
+        relative(
+            root,
+            impacted_modules(&impacted, &project_files, None, Some(&app), root)
+        ),
+        BTreeSet::from(["app/orders.py".to_string()])
+    );
+    Ok(())
+}
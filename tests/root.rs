@@ -0,0 +1,103 @@
+use anyhow::Result;
+use std::fs;
+use tempfile::TempDir;
+
+use snob_lib::utils::{find_project_root, DEFAULT_ROOT_MARKERS};
+
+fn default_markers() -> Vec<String> {
+    DEFAULT_ROOT_MARKERS.map(ToString::to_string).to_vec()
+}
+
+#[test]
+fn test_git_root_takes_precedence_over_closer_markers() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+    let member = root.join("packages/member");
+    fs::create_dir_all(root.join(".git"))?;
+    fs::create_dir_all(member.join("src"))?;
+    fs::write(member.join("pyproject.toml"), "")?;
+
+    assert_eq!(
+        find_project_root(&member.join("src"), &default_markers())?,
+        root
+    );
+    Ok(())
+}
+
+#[test]
+fn test_root_without_git() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+    fs::create_dir_all(root.join("src/pkg"))?;
+    fs::write(root.join("snob.toml"), "")?;
+    fs::write(root.join("src/pyproject.toml"), "")?;
+
+    // `snob.toml` comes before `pyproject.toml`
+    assert_eq!(
+        find_project_root(&root.join("src/pkg"), &default_markers())?,
+        root
+    );
+
+    // other version control systems
+    fs::create_dir_all(root.join("src/.jj"))?;
+    assert_eq!(
+        find_project_root(&root.join("src/pkg"), &default_markers())?,
+        root.join("src")
+    );
+    Ok(())
+}
+
+#[test]
+fn test_custom_markers() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+    fs::create_dir_all(root.join("app"))?;
+    fs::write(root.join("setup.cfg"), "")?;
+
+    assert_eq!(
+        find_project_root(&root.join("app"), &["setup.cfg".to_string()])?,
+        root
+    );
+
+    let error = find_project_root(&root.join("app"), &["tox.ini".to_string()]).unwrap_err();
+    assert!(error.to_string().contains("tox.ini"));
+    Ok(())
+}
+
+#[test]
+fn test_submodule_belongs_to_its_superproject() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+    let nested = root.join("vendor/lib/deps/inner");
+    fs::create_dir_all(root.join(".git/modules/lib/modules/inner"))?;
+    fs::create_dir_all(nested.join("src"))?;
+    fs::write(
+        nested.join(".git"),
+        "gitdir: ../../../../.git/modules/lib/modules/inner\n",
+    )?;
+
+    assert_eq!(
+        find_project_root(&nested.join("src"), &default_markers())?,
+        root
+    );
+    Ok(())
+}
+
+#[test]
+fn test_worktree_is_its_own_root() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let repository = temp_dir.path().join("repository");
+    let worktree = temp_dir.path().join("worktree");
+    fs::create_dir_all(repository.join(".git/worktrees/worktree"))?;
+    fs::create_dir_all(&worktree)?;
+    fs::write(
+        worktree.join(".git"),
+        format!(
+            "gitdir: {}\n",
+            repository.join(".git/worktrees/worktree").display()
+        ),
+    )?;
+
+    assert_eq!(find_project_root(&worktree, &default_markers())?, worktree);
+    Ok(())
+}
@@ -49,7 +49,7 @@ def test_main():
     .into_iter()
     .collect::<FxHashSet<String>>();
 
-    let config = Config::new(&project_path);
+    let config = Config::new(&project_path)?;
     let result = get_impacted_tests_from_changed_files(
         &config,
         &project_path,
@@ -87,7 +87,7 @@ fn test_empty_changes() -> Result<()> {
     // Test with no changes
     let changed_files = FxHashSet::default();
 
-    let config = Config::new(&project_path);
+    let config = Config::new(&project_path)?;
     let result = get_impacted_tests_from_changed_files(
         &config,
         &project_path,
@@ -105,3 +105,50 @@ fn test_empty_changes() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_lookup_roots_outside_target_directory() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let project_path = temp_dir.path().to_path_buf();
+
+    fs::create_dir_all(project_path.join("src/app"))?;
+    fs::create_dir_all(project_path.join("tests"))?;
+    fs::write(project_path.join("src/app/__init__.py"), "")?;
+    fs::write(project_path.join("src/app/main.py"), "def main(): pass")?;
+    fs::write(
+        project_path.join("tests/test_main.py"),
+        "from app.main import main\n",
+    )?;
+
+    // `src/` is only reachable through PYTHONPATH when running from `tests/`
+    setup_pythonpath_for_project(&project_path);
+
+    let changed_files = vec![project_path
+        .join("src/app/main.py")
+        .to_string_lossy()
+        .to_string()]
+    .into_iter()
+    .collect::<FxHashSet<String>>();
+
+    let config = Config::new(&project_path)?;
+    let result = get_impacted_tests_from_changed_files(
+        &config,
+        &project_path.join("tests"),
+        &project_path,
+        &changed_files,
+    )?;
+
+    match result {
+        SnobOutput::Partial(results) => {
+            assert!(results.impacted.contains(
+                &project_path
+                    .join("tests/test_main.py")
+                    .to_string_lossy()
+                    .to_string()
+            ));
+        }
+        SnobOutput::All => panic!("Expected partial results"),
+    }
+
+    Ok(())
+}
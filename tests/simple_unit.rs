@@ -1,4 +1,8 @@
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
+use snob_lib::graph::{
+    dependencies_of, dependents_of, discover_dependencies,
+    discover_impacted_nodes_with_predecessors, import_cycles,
+};
 use snob_lib::utils::{is_test_file, merge_hashmaps};
 
 #[test]
@@ -49,3 +53,140 @@ fn test_merge_hashmaps_empty() {
     let result = merge_hashmaps(&mut empty_maps);
     assert!(result.is_empty());
 }
+
+#[test]
+fn test_discover_dependencies() {
+    // imported file -> importing files
+    let mut graph: FxHashMap<String, FxHashSet<String>> = FxHashMap::default();
+    graph.insert("tax.py".into(), ["orders.py".into()].into_iter().collect());
+    graph.insert(
+        "orders.py".into(),
+        ["test_orders.py".into(), "billing.py".into()]
+            .into_iter()
+            .collect(),
+    );
+    graph.insert(
+        "billing.py".into(),
+        ["test_billing.py".into()].into_iter().collect(),
+    );
+
+    let dependencies =
+        discover_dependencies(&graph, &["test_orders.py".into()].into_iter().collect());
+
+    let mut dependencies = dependencies.into_iter().collect::<Vec<_>>();
+    dependencies.sort();
+    assert_eq!(dependencies, vec!["orders.py", "tax.py", "test_orders.py"]);
+}
+
+#[test]
+fn test_impacted_nodes_chains() {
+    // imported file -> importing files
+    let mut graph: FxHashMap<String, FxHashSet<String>> = FxHashMap::default();
+    graph.insert(
+        "tax.py".into(),
+        ["orders.py".into(), "test_tax.py".into()]
+            .into_iter()
+            .collect(),
+    );
+    graph.insert(
+        "orders.py".into(),
+        ["test_orders.py".into(), "test_tax.py".into()]
+            .into_iter()
+            .collect(),
+    );
+
+    let impacted =
+        discover_impacted_nodes_with_predecessors(&graph, &["tax.py".into()].into_iter().collect());
+
+    let mut nodes = impacted.nodes().cloned().collect::<Vec<_>>();
+    nodes.sort();
+    assert_eq!(
+        nodes,
+        vec!["orders.py", "tax.py", "test_orders.py", "test_tax.py"]
+    );
+    assert_eq!(
+        impacted.chain("test_orders.py").unwrap(),
+        vec!["tax.py", "orders.py", "test_orders.py"]
+    );
+    // the shortest chain
+    assert_eq!(
+        impacted.chain("test_tax.py").unwrap(),
+        vec!["tax.py", "test_tax.py"]
+    );
+    assert_eq!(impacted.chain("tax.py").unwrap(), vec!["tax.py"]);
+    assert!(impacted.chain("billing.py").is_none());
+}
+
+#[test]
+fn test_dependencies_and_dependents_with_depth() {
+    // imported file -> importing files
+    let mut graph: FxHashMap<String, FxHashSet<String>> = FxHashMap::default();
+    graph.insert("tax.py".into(), ["orders.py".into()].into_iter().collect());
+    graph.insert(
+        "orders.py".into(),
+        ["test_orders.py".into(), "billing.py".into()]
+            .into_iter()
+            .collect(),
+    );
+    graph.insert(
+        "billing.py".into(),
+        ["test_billing.py".into()].into_iter().collect(),
+    );
+
+    assert_eq!(
+        dependencies_of(&graph, "test_billing.py", None),
+        vec![
+            ("billing.py".to_string(), 1),
+            ("orders.py".to_string(), 2),
+            ("tax.py".to_string(), 3)
+        ]
+    );
+    assert_eq!(
+        dependencies_of(&graph, "test_billing.py", Some(2)),
+        vec![("billing.py".to_string(), 1), ("orders.py".to_string(), 2)]
+    );
+    assert_eq!(
+        dependents_of(&graph, "tax.py", None),
+        vec![
+            ("orders.py".to_string(), 1),
+            ("billing.py".to_string(), 2),
+            ("test_orders.py".to_string(), 2),
+            ("test_billing.py".to_string(), 3)
+        ]
+    );
+    assert_eq!(
+        dependents_of(&graph, "tax.py", Some(1)),
+        vec![("orders.py".to_string(), 1)]
+    );
+    assert!(dependents_of(&graph, "test_orders.py", None).is_empty());
+}
+
+#[test]
+fn test_import_cycles() {
+    // imported file -> importing files
+    let mut graph: FxHashMap<String, FxHashSet<String>> = FxHashMap::default();
+    let mut import = |importer: &str, imported: &str| {
+        graph
+            .entry(imported.to_string())
+            .or_default()
+            .insert(importer.to_string());
+    };
+    import("a.py", "b.py");
+    import("b.py", "c.py");
+    import("c.py", "a.py");
+    import("c.py", "d.py");
+    import("d.py", "e.py");
+    import("e.py", "d.py");
+    import("self.py", "self.py");
+    import("test_a.py", "a.py");
+
+    assert_eq!(
+        import_cycles(&graph),
+        vec![
+            vec!["a.py".to_string(), "b.py".to_string(), "c.py".to_string()],
+            vec!["d.py".to_string(), "e.py".to_string()],
+            vec!["self.py".to_string()],
+        ]
+    );
+    assert!(import_cycles(&FxHashMap::default()).is_empty());
+}
@@ -0,0 +1,72 @@
+use snob_lib::git::FileChange;
+use snob_lib::stdin::{parse_input, InputFormat};
+
+fn change(path: &str, deleted: bool) -> FileChange {
+    FileChange {
+        path: path.to_string(),
+        deleted,
+    }
+}
+
+#[test]
+fn test_parse_lines() {
+    let input = "src/a.py\n\n  src/b.py \r\n";
+    assert_eq!(
+        parse_input(input, InputFormat::default()),
+        vec![change("src/a.py", false), change("src/b.py", false)]
+    );
+}
+
+#[test]
+fn test_parse_nul_separated() {
+    let input = " leading space.py\0new\nline.py\0";
+    let format = InputFormat {
+        nul_separated: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        parse_input(input, format),
+        vec![
+            change(" leading space.py", false),
+            change("new\nline.py", false)
+        ]
+    );
+}
+
+#[test]
+fn test_parse_name_status_lines() {
+    let input =
+        "M\tsrc/a.py\nD\tsrc/gone.py\nR100\tsrc/old.py\tsrc/new.py\nC075\tsrc/b.py\tsrc/c.py\n";
+    let format = InputFormat {
+        name_status: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        parse_input(input, format),
+        vec![
+            change("src/a.py", false),
+            change("src/gone.py", true),
+            change("src/old.py", true),
+            change("src/new.py", false),
+            change("src/b.py", false),
+            change("src/c.py", false),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_nul_separated_name_status() {
+    let input = "A\0src/with\ttab.py\0R090\0src/old.py\0src/new.py\0";
+    let format = InputFormat {
+        nul_separated: true,
+        name_status: true,
+    };
+    assert_eq!(
+        parse_input(input, format),
+        vec![
+            change("src/with\ttab.py", false),
+            change("src/old.py", true),
+            change("src/new.py", false),
+        ]
+    );
+}
@@ -1,7 +1,10 @@
 use anyhow::Result;
 use rustc_hash::FxHashSet;
 use snob_lib::config::Config;
+use snob_lib::pytest::{PytestSettings, TestFileMatcher};
 use snob_lib::{get_impacted_tests_from_changed_files, SnobOutput};
+use std::fs;
+use tempfile::TempDir;
 
 mod fixtures;
 mod test_utils;
@@ -25,7 +28,7 @@ fn test_different_test_file_patterns() -> Result<()> {
     .into_iter()
     .collect::<FxHashSet<String>>();
 
-    let config = Config::new(&project_path);
+    let config = Config::new(&project_path)?;
     let result = get_impacted_tests_from_changed_files(
         &config,
         &project_path,
@@ -93,7 +96,7 @@ fn test_pattern_project_no_changes_no_tests() -> Result<()> {
     // Test with empty changes
     let changed_files = FxHashSet::default();
 
-    let config = Config::new(&project_path);
+    let config = Config::new(&project_path)?;
     let result = get_impacted_tests_from_changed_files(
         &config,
         &project_path,
@@ -117,3 +120,222 @@ fn test_pattern_project_no_changes_no_tests() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_pytest_settings_from_pyproject() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    fs::write(
+        temp_dir.path().join("pyproject.toml"),
+        r#"
+[tool.pytest.ini_options]
+python_files = ["check_*.py", "*_tests.py"]
+testpaths = "tests integration"
+"#,
+    )?;
+
+    let settings = PytestSettings::read(temp_dir.path());
+
+    assert_eq!(
+        settings,
+        PytestSettings {
+            python_files: Some(vec!["check_*.py".to_string(), "*_tests.py".to_string()]),
+            testpaths: Some(vec!["tests".to_string(), "integration".to_string()]),
+            norecursedirs: None,
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_pytest_settings_from_ini_take_precedence() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    fs::write(
+        temp_dir.path().join("pyproject.toml"),
+        "[tool.pytest.ini_options]\npython_files = [\"check_*.py\"]\n",
+    )?;
+    fs::write(
+        temp_dir.path().join("pytest.ini"),
+        "[pytest]\npython_files =\n    spec_*.py\n    *_spec.py\nnorecursedirs = fixtures\n",
+    )?;
+
+    let settings = PytestSettings::read(temp_dir.path());
+
+    assert_eq!(
+        settings.python_files,
+        Some(vec!["spec_*.py".to_string(), "*_spec.py".to_string()])
+    );
+    assert_eq!(settings.norecursedirs, Some(vec!["fixtures".to_string()]));
+
+    Ok(())
+}
+
+#[test]
+fn test_test_file_matcher_follows_pytest_settings() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+    fs::write(
+        root.join("setup.cfg"),
+        "[tool:pytest]\npython_files = check_*.py *_tests.py\ntestpaths = tests integration\n",
+    )?;
+
+    let matcher = TestFileMatcher::new(&Config::new(root)?, root)?;
+
+    assert!(matcher.is_test_file(root.join("tests/check_orders.py")));
+    assert!(matcher.is_test_file(root.join("integration/api/orders_tests.py")));
+    // outside of testpaths
+    assert!(!matcher.is_test_file(root.join("src/check_orders.py")));
+    // default patterns are replaced
+    assert!(!matcher.is_test_file(root.join("tests/test_orders.py")));
+    // pytest's default norecursedirs
+    assert!(!matcher.is_test_file(root.join("tests/build/check_orders.py")));
+
+    Ok(())
+}
+
+#[test]
+fn test_test_file_matcher_snob_config_overrides_pytest() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+    fs::write(
+        root.join("pytest.ini"),
+        "[pytest]\npython_files = check_*.py\n",
+    )?;
+    fs::write(
+        root.join("snob.toml"),
+        "[tests]\npython-files = [\"test_*.py\"]\nnorecursedirs = [\"fixtures\"]\n",
+    )?;
+
+    let matcher = TestFileMatcher::new(&Config::new(root)?, root)?;
+
+    assert!(matcher.is_test_file(root.join("tests/test_orders.py")));
+    assert!(!matcher.is_test_file(root.join("tests/check_orders.py")));
+    assert!(!matcher.is_test_file(root.join("tests/fixtures/test_data.py")));
+    assert!(matcher.is_test_file(root.join("build/test_orders.py")));
+
+    Ok(())
+}
+
+/// A project with legacy unittest checks and a library module carrying doctests,
+/// neither of them following pytest's file naming conventions
+fn create_ast_detected_tests_project() -> Result<TempDir> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+
+    fs::create_dir_all(root.join("app"))?;
+    fs::create_dir_all(root.join("legacy"))?;
+    fs::write(root.join("app/__init__.py"), "")?;
+    fs::write(
+        root.join("app/pricing.py"),
+        r#"
+def total(prices):
+    """Sum prices.
+
+    >>> total([1, 2])
+    3
+    """
+    return sum(prices)
+"#,
+    )?;
+    fs::write(
+        root.join("app/helpers.py"),
+        "from app.pricing import total\n\ndef helper():\n    \"\"\"No examples here.\"\"\"\n    pass\n",
+    )?;
+    fs::write(root.join("legacy/__init__.py"), "")?;
+    fs::write(
+        root.join("legacy/checks.py"),
+        "import unittest\nfrom app.pricing import total\n\nclass PricingChecks(unittest.TestCase):\n    def test_total(self):\n        pass\n",
+    )?;
+
+    Ok(temp_dir)
+}
+
+fn impacted_tests(root: &std::path::Path, config: &Config) -> Result<Vec<String>> {
+    let changed_files = vec![root.join("app/pricing.py").to_string_lossy().to_string()]
+        .into_iter()
+        .collect::<FxHashSet<String>>();
+    let root = root.to_path_buf();
+    let SnobOutput::Partial(results) =
+        get_impacted_tests_from_changed_files(config, &root, &root, &changed_files)?
+    else {
+        panic!("Expected partial results");
+    };
+    let mut impacted = results
+        .impacted
+        .into_iter()
+        .map(|t| {
+            std::path::Path::new(&t)
+                .strip_prefix(&root)
+                .unwrap()
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect::<Vec<_>>();
+    impacted.sort();
+    Ok(impacted)
+}
+
+#[test]
+fn test_unittest_and_doctest_modules_are_not_tests_by_default() -> Result<()> {
+    let temp_dir = create_ast_detected_tests_project()?;
+    let root = temp_dir.path();
+
+    let config = Config::new(root)?;
+
+    assert!(impacted_tests(root, &config)?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_unittest_test_cases_are_tests() -> Result<()> {
+    let temp_dir = create_ast_detected_tests_project()?;
+    let root = temp_dir.path();
+    fs::write(
+        root.join("snob.toml"),
+        "[tests]\nunittest-test-cases = true\n",
+    )?;
+
+    let config = Config::new(root)?;
+
+    assert_eq!(impacted_tests(root, &config)?, vec!["legacy/checks.py"]);
+
+    Ok(())
+}
+
+#[test]
+fn test_doctest_modules_are_tests() -> Result<()> {
+    let temp_dir = create_ast_detected_tests_project()?;
+    let root = temp_dir.path();
+    fs::write(
+        root.join("snob.toml"),
+        "[tests]\nunittest-test-cases = true\ndoctest-modules = true\n",
+    )?;
+
+    let config = Config::new(root)?;
+
+    // helpers.py is impacted as well but has no doctest examples
+    assert_eq!(
+        impacted_tests(root, &config)?,
+        vec!["app/pricing.py", "legacy/checks.py"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_nested_doctest_modules_only_apply_below_their_directory() -> Result<()> {
+    let temp_dir = create_ast_detected_tests_project()?;
+    let root = temp_dir.path();
+    fs::write(
+        root.join("legacy/snob.toml"),
+        "[tests]\nunittest-test-cases = true\ndoctest-modules = true\n",
+    )?;
+
+    let config = Config::new(root)?;
+
+    // app/pricing.py has doctest examples, but lives outside of legacy/
+    assert_eq!(impacted_tests(root, &config)?, vec!["legacy/checks.py"]);
+
+    Ok(())
+}
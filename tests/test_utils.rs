@@ -1,5 +1,12 @@
+use rustc_hash::FxHashSet;
 use std::env;
+use std::fs;
 use std::path::Path;
+use tempfile::TempDir;
+
+use snob_lib::analysis::{Analysis, Layout};
+use snob_lib::config::Config;
+use snob_lib::source::WorkingTree;
 
 // Use the same separator constants as the main codebase
 #[cfg(target_os = "windows")]
@@ -58,3 +65,35 @@ pub fn setup_pythonpath_for_paths(paths: &[&Path]) {
 
     env::set_var("PYTHONPATH", new_pythonpath);
 }
+
+/// Create a project (marked as the root of a git repository) made of `files`,
+/// given as (relative path, contents) pairs
+#[allow(dead_code)]
+pub fn create_project(files: &[(&str, &str)]) -> anyhow::Result<TempDir> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+    fs::create_dir_all(root.join(".git"))?;
+    for (file, contents) in files {
+        let path = root.join(file);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, contents)?;
+    }
+    Ok(temp_dir)
+}
+
+/// Analyze the working tree of the project at `root`, using its own configuration
+#[allow(dead_code)]
+pub fn analyze(root: &Path) -> anyhow::Result<Analysis> {
+    let config = Config::new(root)?;
+    let layout = Layout::new(root, root, root, None)?;
+    Analysis::new(
+        &WorkingTree,
+        &layout,
+        &config,
+        root,
+        &FxHashSet::default(),
+        None,
+    )
+}
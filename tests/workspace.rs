@@ -0,0 +1,202 @@
+use anyhow::Result;
+use rustc_hash::FxHashSet;
+use std::fs;
+use std::path::PathBuf;
+use tempfile::TempDir;
+
+use snob_lib::config::Config;
+use snob_lib::workspace::{discover_workspace_members, group_by_member};
+use snob_lib::{get_impacted_tests_from_changed_files, SnobOutput};
+
+mod test_utils;
+use test_utils::analyze;
+
+/// Creates a uv workspace with two members:
+/// packages/billing (src layout) and packages/orders, whose tests import billing
+fn create_uv_workspace() -> Result<TempDir> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+
+    fs::write(
+        root.join("pyproject.toml"),
+        r#"
+[tool.uv.workspace]
+members = ["packages/*"]
+exclude = ["packages/legacy"]
+"#,
+    )?;
+
+    for member in ["billing", "orders", "legacy"] {
+        let member_root = root.join("packages").join(member);
+        fs::create_dir_all(member_root.join("src").join(member))?;
+        fs::create_dir_all(member_root.join("tests"))?;
+        fs::write(
+            member_root.join("pyproject.toml"),
+            format!("[project]\nname = \"{member}\"\n"),
+        )?;
+        fs::write(member_root.join("src").join(member).join("__init__.py"), "")?;
+    }
+
+    fs::write(
+        root.join("packages/billing/src/billing/invoices.py"),
+        "def invoice(): pass\n",
+    )?;
+    fs::write(
+        root.join("packages/billing/tests/test_invoices.py"),
+        "from billing.invoices import invoice\n",
+    )?;
+    fs::write(
+        root.join("packages/orders/tests/test_orders.py"),
+        "from billing.invoices import invoice\nfrom orders import *\n",
+    )?;
+
+    Ok(temp_dir)
+}
+
+#[test]
+fn test_discover_uv_workspace_members() -> Result<()> {
+    let temp_dir = create_uv_workspace()?;
+    let root = temp_dir.path();
+
+    let mut members = discover_workspace_members(root)?;
+    members.sort_by(|a, b| a.name.cmp(&b.name));
+
+    assert_eq!(
+        members.iter().map(|m| m.name.as_str()).collect::<Vec<_>>(),
+        vec!["billing", "orders"]
+    );
+    assert_eq!(members[0].root, root.join("packages/billing"));
+    assert_eq!(members[0].import_root, root.join("packages/billing/src"));
+
+    Ok(())
+}
+
+#[test]
+fn test_discover_poetry_path_dependencies() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+
+    fs::create_dir_all(root.join("libs/core/core"))?;
+    fs::write(
+        root.join("libs/core/pyproject.toml"),
+        "[tool.poetry]\nname = \"core\"\n",
+    )?;
+    fs::write(
+        root.join("pyproject.toml"),
+        r#"
+[tool.poetry]
+name = "app"
+
+[tool.poetry.dependencies]
+python = "^3.11"
+core = { path = "libs/core", develop = true }
+
+[tool.poetry.group.dev.dependencies]
+core-again = { path = "./libs/core/../core" }
+"#,
+    )?;
+
+    let members = discover_workspace_members(root)?;
+
+    assert_eq!(
+        members.iter().map(|m| m.name.as_str()).collect::<Vec<_>>(),
+        vec!["core", "app"]
+    );
+    assert_eq!(members[0].root, root.join("libs/core"));
+    assert_eq!(members[0].import_root, root.join("libs/core"));
+
+    Ok(())
+}
+
+#[test]
+fn test_path_dependencies_outside_of_the_repository_are_ignored() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path().join("repo");
+
+    fs::create_dir_all(root.join("libs/core/core"))?;
+    fs::create_dir_all(temp_dir.path().join("shared/shared"))?;
+    fs::write(
+        root.join("libs/core/pyproject.toml"),
+        "[tool.poetry]\nname = \"core\"\n",
+    )?;
+    fs::write(
+        temp_dir.path().join("shared/pyproject.toml"),
+        "[tool.poetry]\nname = \"shared\"\n",
+    )?;
+    fs::write(temp_dir.path().join("shared/shared/__init__.py"), "")?;
+    fs::write(root.join("test_app.py"), "import shared\nimport core\n")?;
+    fs::write(
+        root.join("pyproject.toml"),
+        r#"
+[tool.poetry]
+name = "app"
+
+[tool.poetry.dependencies]
+core = { path = "libs/core" }
+shared = { path = "../shared" }
+"#,
+    )?;
+
+    let members = discover_workspace_members(&root)?;
+    assert_eq!(
+        members.iter().map(|m| m.name.as_str()).collect::<Vec<_>>(),
+        vec!["core", "app"]
+    );
+
+    // the files of the path dependency are not analyzed
+    let analysis = analyze(&root)?;
+    assert!(analysis
+        .project_files
+        .iter()
+        .all(|f| std::path::Path::new(f).starts_with(&root)));
+
+    Ok(())
+}
+
+#[test]
+fn test_single_project_has_no_members() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    fs::write(
+        temp_dir.path().join("pyproject.toml"),
+        "[project]\nname = \"app\"\n",
+    )?;
+
+    assert!(discover_workspace_members(temp_dir.path())?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_workspace_impacted_tests_grouped_by_member() -> Result<()> {
+    let temp_dir = create_uv_workspace()?;
+    let root = temp_dir.path().to_path_buf();
+
+    let changed_files = vec![root
+        .join("packages/billing/src/billing/invoices.py")
+        .to_string_lossy()
+        .to_string()]
+    .into_iter()
+    .collect::<FxHashSet<String>>();
+
+    let config = Config::new(&root)?;
+    let result = get_impacted_tests_from_changed_files(&config, &root, &root, &changed_files)?;
+
+    let SnobOutput::Partial(results) = result else {
+        panic!("Expected partial results");
+    };
+
+    let members = discover_workspace_members(&root)?;
+    let groups = group_by_member(&results.impacted, &members, &root);
+
+    assert_eq!(
+        groups.get(&root.join("packages/billing")),
+        Some(&vec![PathBuf::from("tests/test_invoices.py")])
+    );
+    assert_eq!(
+        groups.get(&root.join("packages/orders")),
+        Some(&vec![PathBuf::from("tests/test_orders.py")])
+    );
+    assert_eq!(groups.len(), 2);
+
+    Ok(())
+}